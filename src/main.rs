@@ -1,37 +1,270 @@
 extern crate argparse;
+extern crate satyrs;
 
 use std::fs::File;
+use std::io;
+use std::process;
 use argparse::{ArgumentParser, Store, StoreTrue};
-use satyrs::cnf::CNF;
-use satyrs::dpll;
+use satyrs::satyrs::cdcl;
+use satyrs::satyrs::cnf::{self, Assignment, CNF, DimacsError};
+use satyrs::satyrs::cube;
+use satyrs::satyrs::dpll;
+use satyrs::satyrs::lookahead;
+use satyrs::satyrs::encode::PbEncoding;
+use satyrs::satyrs::maxsat;
+use satyrs::satyrs::opb;
+use satyrs::satyrs::optimize;
+use satyrs::satyrs::parallel;
+use satyrs::satyrs::portfolio;
+use satyrs::satyrs::preprocess::{self, Reconstruction};
+use satyrs::satyrs::qbf;
 
-// extern crate satyrs;
-mod satyrs;
+/// Say what is wrong with `filename` and quit.
+fn exit_with(filename: &str, message: &str) -> ! {
+    eprintln!("{}: error: {}", filename, message);
+    process::exit(1);
+}
+
+/// Open `filename`, or say why it can't be opened and quit.
+fn open(filename: &str) -> File {
+    File::open(filename).unwrap_or_else(|error| {
+        exit_with(filename, &format!("could not open file: {}", error))
+    })
+}
+
+/// `exit_with` for a DIMACS error, which knows where it is, in the `file:line:column` form
+/// editors jump to.
+fn exit_with_dimacs(filename: &str, error: DimacsError) -> ! {
+    let location = if error.column > 0 {
+        format!("{}:{}:{}", filename, error.line, error.column)
+    } else {
+        format!("{}:{}", filename, error.line)
+    };
+    if error.token.is_empty() {
+        exit_with(&location, error.kind.message());
+    }
+    exit_with(&location, &format!("{}\n  found `{}`", error.kind.message(), error.token))
+}
 
 fn main() {
     let mut filename = String::new();
     let mut verbose: bool = false;
+    let mut race: bool = false;
+    let mut look: bool = false;
+    let mut simplify: bool = false;
+    let mut strict: bool = false;
+    let mut quantified: bool = false;
+    let mut inprocess: usize = 0;
+    let mut threads: usize = 1;
+    let mut depth: usize = 0;
+    let mut cutoff: usize = 0;
+    let mut icnf = String::new();
     {
         // this block limits scope of borrows by ap.refer() method
         let mut ap = ArgumentParser::new();
         ap.set_description("Satyrs: A lustful, drunken SAT solver");
         ap.refer(&mut filename)
-          .add_argument("filename", Store, "SAT filename to read")
+          .add_argument("filename",
+                        Store,
+                        "SAT filename to read, or - for DIMACS on standard input")
           .required();
         ap.refer(&mut verbose)
           .add_option(&["-v", "--verbose"], StoreTrue, "Be verbose");
+        ap.refer(&mut strict)
+          .add_option(&["--strict"],
+                      StoreTrue,
+                      "Warn where a DIMACS file departs from the format");
+        ap.refer(&mut quantified)
+          .add_option(&["--qdimacs"],
+                      StoreTrue,
+                      "Read QDIMACS, as for a .qdimacs file; needed with - for standard input");
+        ap.refer(&mut race)
+          .add_option(&["-p", "--portfolio"],
+                      StoreTrue,
+                      "Race local search against CDCL");
+        ap.refer(&mut simplify)
+          .add_option(&["-s", "--preprocess"],
+                      StoreTrue,
+                      "Simplify the formula before solving");
+        ap.refer(&mut inprocess)
+          .add_option(&["--inprocess"],
+                      Store,
+                      "Eliminate blocked clauses during DPLL, every this many splits");
+        ap.refer(&mut look)
+          .add_option(&["-l", "--lookahead"],
+                      StoreTrue,
+                      "Branch by lookahead instead of Jeroslow-Wang");
+        ap.refer(&mut threads)
+          .add_option(&["-j", "--threads"],
+                      Store,
+                      "Run this many CDCL solvers in parallel, sharing learned clauses");
+        ap.refer(&mut depth)
+          .add_option(&["--cube"],
+                      Store,
+                      "Cube and conquer, with cubes of up to this many decisions");
+        ap.refer(&mut cutoff)
+          .add_option(&["--cutoff"],
+                      Store,
+                      "Stop cubing once this many free variables are left; implies --cube");
+        ap.refer(&mut icnf)
+          .add_option(&["--icnf"],
+                      Store,
+                      "Write the cubes to this iCNF file instead of solving; implies --cube");
         ap.parse_args_or_exit();
     }
 
-    // Read the file
-    let f: File = File::open(filename).expect("Could not open file");
+    if filename.ends_with(".wcnf") {
+        let wcnf = match maxsat::parse_wcnf_file(open(&filename)) {
+            Ok(wcnf) => wcnf,
+            Err(error) => exit_with(&filename, error),
+        };
+        match maxsat::solve(&wcnf, PbEncoding::GeneralizedTotalizer, verbose) {
+            Some((cost, solution)) => {
+                println!("Optimum found!");
+                println!("Cost: {}\nSAT Format: {}", cost, cnf::format_output(&solution));
+            }
+            None => println!("Unsatisfiable"),
+        }
+        return;
+    }
+
+    if quantified || filename.ends_with(".qdimacs") {
+        let parsed = if filename == "-" {
+            let stdin = io::stdin();
+            qbf::parse_qdimacs(stdin.lock())
+        } else {
+            qbf::parse_qdimacs_file(open(&filename))
+        };
+        let qbf = match parsed {
+            Ok(qbf) => qbf,
+            Err(error) if filename == "-" => exit_with_dimacs("<stdin>", error),
+            Err(error) => exit_with_dimacs(&filename, error),
+        };
+        match qbf::solve(&qbf, verbose) {
+            Some(certificate) => {
+                println!("True");
+                println!("Certificate: {:?}", certificate);
+            }
+            None => println!("False"),
+        }
+        return;
+    }
 
-    // TODO: This is definitely not the correct way to handle errors
-    let cnf: CNF = satyrs::cnf::parse_dimacs_file(f).expect("Dimacs Error");
-    let solvable = dpll::DPLL(&cnf, verbose);
+    // For OPB files, only report the variables of the original problem, not the encoder's.
+    let (cnf, nvar): (CNF, i32) = if filename.ends_with(".opb") {
+        let opb = match opb::parse_opb_file(open(&filename), PbEncoding::Bdd) {
+            Ok(opb) => opb,
+            Err(error) => exit_with(&filename, error),
+        };
+        if let Some(objective) = opb.objective {
+            // Report every improving solution, as the PB competitions do
+            let report = |cost: i64, _: &Vec<bool>| println!("o {}", cost);
+            let solution = optimize::minimize(&opb.cnf, &objective, PbEncoding::Bdd, None, report);
+            match solution {
+                Some(mut solution) => {
+                    solution.assignment.truncate(opb.nvar as usize);
+                    println!("Optimum found!");
+                    println!("Cost: {}\nSAT Format: {}",
+                             solution.cost,
+                             cnf::format_output(&solution.assignment));
+                }
+                None => println!("Unsatisfiable"),
+            }
+            return;
+        }
+        (opb.cnf, opb.nvar)
+    } else {
+        // A filename of `-` reads DIMACS from standard input, for formulas generated on the fly
+        let parsed = if filename == "-" {
+            let stdin = io::stdin();
+            cnf::parse_dimacs_with_report(stdin.lock(), strict)
+        } else {
+            cnf::parse_dimacs_file_with_report(open(&filename), strict)
+        };
+        let (cnf, report) = match parsed {
+            Ok(parsed) => parsed,
+            Err(error) if filename == "-" => exit_with_dimacs("<stdin>", error),
+            Err(error) => exit_with_dimacs(&filename, error),
+        };
+        for warning in &report.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        if verbose && report != cnf::ParseReport::default() {
+            eprintln!("Dropped while parsing: {:?}", report);
+        }
+        let nvar = cnf.nvar;
+        (cnf, nvar)
+    };
+    let mut reconstruction = Reconstruction::new();
+    let cnf = if simplify {
+        match preprocess::preprocess(&cnf, &preprocess::Config::default()) {
+            Some(preprocessed) => {
+                if verbose {
+                    for &(technique, ref stats) in &preprocessed.stats {
+                        println!("{:?}: {:?}", technique, stats);
+                    }
+                }
+                reconstruction = preprocessed.reconstruction;
+                preprocessed.cnf
+            }
+            None => {
+                println!("Unsatisfiable");
+                return;
+            }
+        }
+    } else {
+        cnf
+    };
+    if race {
+        match portfolio::solve(&cnf, &portfolio::Config::default(), verbose) {
+            (Some(mut solution), engine) => {
+                reconstruction.extend(&mut solution);
+                solution.truncate(nvar as usize);
+                println!("Satisfiable! ({:?})", engine);
+                println!("SAT Format: {}", cnf::format_output(&solution));
+            }
+            (None, _) => println!("Unsatisfiable"),
+        }
+        return;
+    }
+    // The cubing options are no use on their own, so any of them turns cubing on
+    if depth > 0 || cutoff > 0 || !icnf.is_empty() {
+        let config = cube::Config {
+            depth: if depth > 0 { depth } else { cube::Config::default().depth },
+            cutoff,
+        };
+        let cubes = cube::cube(&cnf, config);
+        if !icnf.is_empty() {
+            let mut out = File::create(&icnf).expect("Could not create file");
+            cube::write_icnf(&mut out, &cnf, &cubes).expect("iCNF Error");
+            println!("Wrote {} cubes", cubes.len());
+            return;
+        }
+        report(cube::conquer(&cnf, &cubes, threads, &cdcl::Config::default(), verbose),
+               nvar,
+               &reconstruction);
+        return;
+    }
+    if look {
+        report(lookahead::solve(&cnf, verbose), nvar, &reconstruction);
+        return;
+    }
+    if threads > 1 {
+        report(parallel::solve(&cnf, &parallel::diversify(threads), 8, verbose),
+               nvar,
+               &reconstruction);
+        return;
+    }
+    let options = dpll::Options {
+        inprocess: if inprocess > 0 { Some(inprocess) } else { None },
+        ..dpll::Options::default()
+    };
+    let solvable = dpll::DPLL_with(&cnf, verbose, &options);
     match solvable {
-        Some((solution, partial)) => {
-            let solution_str = satyrs::cnf::format_output(&solution);
+        Some((mut solution, partial)) => {
+            reconstruction.extend(&mut solution);
+            solution.truncate(nvar as usize);
+            let solution_str = cnf::format_output(&solution);
             println!("Satisfiable!");
             println!("SAT Format: {}\nSolution: {:?}\nPartial {}\n",
                      solution_str,
@@ -41,3 +274,17 @@ fn main() {
         None => println!("Unsatisfiable"),
     }
 }
+
+/// Print the model of a solver that doesn't give more than that, for the first `nvar` variables,
+/// after undoing preprocessing.
+fn report(solution: Option<Assignment>, nvar: i32, reconstruction: &Reconstruction) {
+    match solution {
+        Some(mut solution) => {
+            reconstruction.extend(&mut solution);
+            solution.truncate(nvar as usize);
+            println!("Satisfiable!");
+            println!("SAT Format: {}", cnf::format_output(&solution));
+        }
+        None => println!("Unsatisfiable"),
+    }
+}
@@ -0,0 +1,962 @@
+//! Conflict-driven clause learning, for when the recursive `satyrs::dpll` is too slow.
+//!
+//! The solver keeps one trail of assignments instead of copying the formula at every node. Unit
+//! propagation uses two watched literals per clause; every conflict is analysed down to its first
+//! unique implication point, and the resulting clause is learned before backjumping. Branching
+//! follows VSIDS activities with saved phases, and the search restarts on a configurable schedule.
+//!
+//! Between restarts, clauses can be vivified: assigning the negations of a clause's literals one
+//! by one, with the clause itself left out, often propagates to a conflict or to another of its
+//! literals before the end, and then the literals so far make a shorter clause that still follows
+//! from the formula.
+//!
+//! Literals use the same encoding as `CNF`: `var << 1`, with the low bit set for negation.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use satyrs::cnf::{Assignment, CNF};
+use satyrs::exchange::Exchange;
+use satyrs::sls::XorShift;
+
+/// How the branching variable is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Bump the variables of every learned clause, and decay the rest.
+    Vsids,
+    /// A static order by Jeroslow-Wang score, as `satyrs::heuristics::jw` would give at the root.
+    Jw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Restarts {
+    Never,
+    /// The Luby sequence, scaled by this many conflicts.
+    Luby(u64),
+    /// Restart after `first` conflicts, then after `factor` times as many as the last time.
+    Geometric { first: u64, factor: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub heuristic: Heuristic,
+    /// VSIDS activities are divided by this after every conflict.
+    pub decay: f64,
+    pub restarts: Restarts,
+    /// Probability of branching on a random variable instead.
+    pub random_freq: f64,
+    pub seed: u64,
+    /// Value tried first for variables that have never been assigned.
+    pub phase: bool,
+    /// Time to spend vivifying clauses after every restart. Each round picks up where the last
+    /// one ran out of time.
+    pub vivify: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            heuristic: Heuristic::Vsids,
+            decay: 0.95,
+            restarts: Restarts::Luby(100),
+            random_freq: 0.0,
+            seed: 1,
+            phase: false,
+            vivify: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Satisfiable,
+    /// Unsatisfiable, or unsatisfiable under the given assumptions.
+    Unsatisfiable,
+    /// The search was stopped from outside before it finished.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub decisions: u64,
+    pub propagations: u64,
+    pub conflicts: u64,
+    pub restarts: u64,
+    pub learned: u64,
+    /// Clauses shortened by vivification.
+    pub vivified: u64,
+}
+
+struct Clause {
+    /// While the clause is the reason for an assignment, the assigned literal is `lits[0]`.
+    lits: Vec<usize>,
+    learnt: bool,
+    /// Number of distinct decision levels among the literals when the clause was learned.
+    lbd: usize,
+    deleted: bool,
+}
+
+/// Max-heap of variables ordered by activity, with a position index so that bumped variables can
+/// be moved up in place.
+struct VarHeap {
+    heap: Vec<usize>,
+    position: Vec<Option<usize>>,
+}
+
+impl VarHeap {
+    fn new(nvar: usize) -> VarHeap {
+        VarHeap {
+            heap: Vec::new(),
+            position: vec![None; nvar + 1],
+        }
+    }
+
+    fn contains(&self, var: usize) -> bool {
+        self.position[var].is_some()
+    }
+
+    fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            return;
+        }
+        self.position[var] = Some(self.heap.len());
+        self.heap.push(var);
+        self.sift_up(self.heap.len() - 1, activity);
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        let top = self.heap.swap_remove(0);
+        self.position[top] = None;
+        if !self.heap.is_empty() {
+            self.position[self.heap[0]] = Some(0);
+            self.sift_down(0, activity);
+        }
+        Some(top)
+    }
+
+    /// Restore the heap after the activity of `var` went up.
+    fn increased(&mut self, var: usize, activity: &[f64]) {
+        if let Some(i) = self.position[var] {
+            self.sift_up(i, activity);
+        }
+    }
+
+    fn sift_up(&mut self, mut i: usize, activity: &[f64]) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if activity[self.heap[i]] <= activity[self.heap[parent]] {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize, activity: &[f64]) {
+        loop {
+            let mut largest = i;
+            for child in [2 * i + 1, 2 * i + 2].iter() {
+                if *child < self.heap.len() &&
+                   activity[self.heap[*child]] > activity[self.heap[largest]] {
+                    largest = *child;
+                }
+            }
+            if largest == i {
+                break;
+            }
+            self.swap(i, largest);
+            i = largest;
+        }
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.position[self.heap[i]] = Some(i);
+        self.position[self.heap[j]] = Some(j);
+    }
+}
+
+/// Value of an internal literal under `assigns`, if its variable is assigned.
+fn value_of(assigns: &[Option<bool>], lit: usize) -> Option<bool> {
+    assigns[lit >> 1].map(|v| v == (lit & 1 == 0))
+}
+
+pub fn to_dimacs(lit: usize) -> i32 {
+    if lit & 1 == 0 { (lit >> 1) as i32 } else { -((lit >> 1) as i32) }
+}
+
+pub fn from_dimacs(lit: i32) -> usize {
+    if lit > 0 { (lit as usize) << 1 } else { ((-lit) as usize) << 1 | 1 }
+}
+
+/// Element `i` (from 0) of the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, ...
+fn luby(mut i: u64) -> u64 {
+    // Find the finite subsequence that contains index i, and its size
+    let mut size = 1;
+    let mut seq = 0;
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+pub struct Solver {
+    nvar: usize,
+    config: Config,
+    clauses: Vec<Clause>,
+    /// For every literal, the clauses watching it, to be visited when it becomes false.
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    trail: Vec<usize>,
+    /// Where each decision level starts on the trail.
+    trail_lim: Vec<usize>,
+    /// Trail position of the next literal to propagate.
+    qhead: usize,
+    activity: Vec<f64>,
+    var_inc: f64,
+    order: VarHeap,
+    /// Last value of every variable, tried first when branching on it again.
+    polarity: Vec<bool>,
+    seen: Vec<bool>,
+    rng: XorShift,
+    /// False once the clauses have been found unsatisfiable without assumptions.
+    ok: bool,
+    learnts: usize,
+    max_learnts: f64,
+    model: Assignment,
+    /// The clause to vivify next.
+    vivify_next: usize,
+    stop: Option<Arc<AtomicBool>>,
+    /// Shared clause buffer, with this solver's ID in it and how far it has read.
+    exchange: Option<(Arc<Exchange>, usize, u64)>,
+    /// Values to take as saved phases at every restart, published by another engine.
+    phases: Option<Arc<Mutex<Assignment>>>,
+    pub stats: Stats,
+}
+
+impl Solver {
+    pub fn new(cnf: &CNF, config: Config) -> Solver {
+        let nvar = cnf.nvar as usize;
+        let mut solver = Solver {
+            nvar,
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * nvar + 2],
+            assigns: vec![None; nvar + 1],
+            level: vec![0; nvar + 1],
+            reason: vec![None; nvar + 1],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            activity: vec![0.0; nvar + 1],
+            var_inc: 1.0,
+            order: VarHeap::new(nvar),
+            polarity: vec![config.phase; nvar + 1],
+            seen: vec![false; nvar + 1],
+            rng: XorShift::new(config.seed),
+            ok: true,
+            learnts: 0,
+            max_learnts: 0.0,
+            model: Vec::new(),
+            vivify_next: 0,
+            stop: None,
+            exchange: None,
+            phases: None,
+            stats: Stats::default(),
+            config,
+        };
+
+        for clause in cnf.sorted_clauses() {
+            // Start the activities at the one-sided Jeroslow-Wang score of either polarity
+            for lit in &clause {
+                solver.activity[(lit >> 1) as usize] += (2f64).powi(-(clause.len() as i32));
+            }
+            solver.add_internal(clause.iter().map(|l| *l as usize).collect(), false);
+        }
+        for var in 1..nvar + 1 {
+            solver.order.insert(var, &solver.activity);
+        }
+        solver.max_learnts = (solver.clauses.len() as f64 / 3.0).max(1000.0);
+        solver
+    }
+
+    /// Abandon `solve` with `Status::Unknown` once `stop` is set.
+    pub fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = Some(stop);
+    }
+
+    /// Publish short learned clauses to `exchange` as solver `id`, and pick up the clauses of
+    /// the other solvers on every restart. All of them must be solving the same formula.
+    pub fn share(&mut self, exchange: Arc<Exchange>, id: usize) {
+        self.exchange = Some((exchange, id, 0));
+    }
+
+    /// Take the values in `phases` as the saved phases when solving starts and on every restart,
+    /// so that another engine, such as local search, can steer the search. Variables past the end
+    /// keep their own. May be updated while the search runs.
+    pub fn follow_phases(&mut self, phases: Arc<Mutex<Assignment>>) {
+        self.phases = Some(phases);
+    }
+
+    fn take_phases(&mut self) {
+        if let Some(ref phases) = self.phases {
+            for (i, &value) in phases.lock().unwrap().iter().take(self.nvar).enumerate() {
+                self.polarity[i + 1] = value;
+            }
+        }
+    }
+
+    /// The model found by the last satisfiable call to `solve`, indexed like an `Assignment`.
+    pub fn model(&self) -> &Assignment {
+        &self.model
+    }
+
+    /// Add a clause of DIMACS literals between calls to `solve`. Returns false if the clauses
+    /// have become unsatisfiable.
+    pub fn add_clause(&mut self, clause: &[i32]) -> bool {
+        assert!(clause.iter().all(|l| l.unsigned_abs() as usize <= self.nvar),
+                "Variable out of range");
+        self.cancel_until(0);
+        self.add_internal(clause.iter().map(|l| from_dimacs(*l)).collect(), false);
+        self.ok
+    }
+
+    pub fn solve(&mut self) -> Status {
+        self.solve_with(&[])
+    }
+
+    /// Solve with the DIMACS literals in `assumptions` taken as true. An unsatisfiable answer
+    /// then only holds under the assumptions, and the solver can be called again.
+    pub fn solve_with(&mut self, assumptions: &[i32]) -> Status {
+        if !self.ok {
+            return Status::Unsatisfiable;
+        }
+        let assumptions: Vec<usize> = assumptions.iter().map(|l| from_dimacs(*l)).collect();
+        let mut restarts = 0;
+        let mut budget = match self.config.restarts {
+            Restarts::Geometric { first, .. } => first as f64,
+            _ => 0.0,
+        };
+        self.take_phases();
+        loop {
+            let limit = match self.config.restarts {
+                Restarts::Never => u64::MAX,
+                Restarts::Luby(unit) => unit * luby(restarts),
+                Restarts::Geometric { factor, .. } => {
+                    let limit = budget as u64;
+                    budget *= factor;
+                    limit
+                }
+            };
+            if let Some(status) = self.search(limit, &assumptions) {
+                if status == Status::Satisfiable {
+                    self.model = self.assigns[1..].iter().map(|v| v.unwrap_or(true)).collect();
+                }
+                self.cancel_until(0);
+                return status;
+            }
+            restarts += 1;
+            self.stats.restarts += 1;
+            self.import();
+            if let Some(budget) = self.config.vivify {
+                self.vivify(budget);
+            }
+            self.take_phases();
+            if !self.ok {
+                return Status::Unsatisfiable;
+            }
+        }
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn value(&self, lit: usize) -> Option<bool> {
+        value_of(&self.assigns, lit)
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.as_ref().is_some_and(|s| s.load(Ordering::Relaxed))
+    }
+
+    fn enqueue(&mut self, lit: usize, reason: Option<usize>) {
+        let var = lit >> 1;
+        self.assigns[var] = Some(lit & 1 == 0);
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Add a clause at decision level 0, simplified against the assignments made there.
+    fn add_internal(&mut self, mut lits: Vec<usize>, learnt: bool) {
+        if !self.ok {
+            return;
+        }
+        lits.sort();
+        lits.dedup();
+        if lits.windows(2).any(|w| w[0] ^ 1 == w[1]) ||
+           lits.iter().any(|l| self.value(*l) == Some(true)) {
+            return;
+        }
+        lits.retain(|l| self.value(*l).is_none());
+        match lits.len() {
+            0 => self.ok = false,
+            1 => {
+                self.enqueue(lits[0], None);
+                if self.propagate().is_some() {
+                    self.ok = false;
+                }
+            }
+            _ => {
+                // Clauses learned elsewhere come without their LBD; they span at most as many
+                // levels as they have literals, which leaves them to `reduce` like the rest
+                let lbd = if learnt { lits.len() } else { 0 };
+                self.attach(lits, learnt, lbd);
+            }
+        }
+    }
+
+    fn attach(&mut self, lits: Vec<usize>, learnt: bool, lbd: usize) -> usize {
+        let id = self.clauses.len();
+        self.watches[lits[0]].push(id);
+        self.watches[lits[1]].push(id);
+        if learnt {
+            self.learnts += 1;
+        }
+        self.clauses.push(Clause {
+            lits,
+            learnt,
+            lbd,
+            deleted: false,
+        });
+        id
+    }
+
+    /// Propagate every literal on the trail that hasn't been yet. Returns a conflicting clause.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = self.trail[self.qhead] ^ 1;
+            self.qhead += 1;
+            self.stats.propagations += 1;
+
+            let mut watchers = std::mem::take(&mut self.watches[false_lit]);
+            let mut kept = 0;
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watchers.len() {
+                let id = watchers[i];
+                i += 1;
+                let assigns = &self.assigns;
+                let clause = &mut self.clauses[id];
+                if clause.deleted {
+                    // Drop the watch lazily
+                    continue;
+                }
+                let lits = &mut clause.lits;
+                if lits[0] == false_lit {
+                    lits.swap(0, 1);
+                }
+                if value_of(assigns, lits[0]) == Some(true) {
+                    watchers[kept] = id;
+                    kept += 1;
+                    continue;
+                }
+                if let Some(k) = (2..lits.len())
+                                     .find(|k| value_of(assigns, lits[*k]) != Some(false)) {
+                    lits.swap(1, k);
+                    self.watches[lits[1]].push(id);
+                    continue;
+                }
+                watchers[kept] = id;
+                kept += 1;
+                let first = lits[0];
+                if value_of(assigns, first) == Some(false) {
+                    conflict = Some(id);
+                    while i < watchers.len() {
+                        watchers[kept] = watchers[i];
+                        kept += 1;
+                        i += 1;
+                    }
+                } else {
+                    self.enqueue(first, Some(id));
+                }
+            }
+            watchers.truncate(kept);
+            self.watches[false_lit] = watchers;
+            if conflict.is_some() {
+                self.qhead = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        if self.config.heuristic != Heuristic::Vsids {
+            return;
+        }
+        self.activity[var] += self.var_inc;
+        if self.activity[var] > 1e100 {
+            for a in self.activity.iter_mut() {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+        self.order.increased(var, &self.activity);
+    }
+
+    /// Learn a clause from the conflict, with its asserting literal first and a literal of the
+    /// backjump level second. Returns the clause and the level to backjump to.
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<usize>, usize) {
+        let mut learnt = vec![0];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut implied: Option<usize> = None;
+        loop {
+            let lits = self.clauses[conflict].lits.clone();
+            // The first literal of a reason is the one it implied, which is already counted
+            let skip = if implied.is_some() { 1 } else { 0 };
+            for &q in &lits[skip..] {
+                let var = q >> 1;
+                if !self.seen[var] && self.level[var] > 0 {
+                    self.seen[var] = true;
+                    self.bump(var);
+                    if self.level[var] >= self.decision_level() {
+                        pending += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+            // Walk back along the trail to the next literal involved in the conflict
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index] >> 1] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            implied = Some(lit);
+            self.seen[lit >> 1] = false;
+            pending -= 1;
+            if pending == 0 {
+                break;
+            }
+            conflict = self.reason[lit >> 1].unwrap();
+        }
+        learnt[0] = implied.unwrap() ^ 1;
+
+        // Drop literals implied by other literals of the clause
+        let mut minimized = vec![learnt[0]];
+        for &q in &learnt[1..] {
+            let redundant = match self.reason[q >> 1] {
+                None => false,
+                Some(r) => {
+                    self.clauses[r].lits[1..].iter().all(|l| {
+                        self.seen[l >> 1] || self.level[l >> 1] == 0
+                    })
+                }
+            };
+            if !redundant {
+                minimized.push(q);
+            }
+        }
+        for &q in &learnt[1..] {
+            self.seen[q >> 1] = false;
+        }
+
+        let mut backjump = 0;
+        if minimized.len() > 1 {
+            let mut max = 1;
+            for i in 2..minimized.len() {
+                if self.level[minimized[i] >> 1] > self.level[minimized[max] >> 1] {
+                    max = i;
+                }
+            }
+            minimized.swap(1, max);
+            backjump = self.level[minimized[1] >> 1];
+        }
+        (minimized, backjump)
+    }
+
+    fn lbd(&self, lits: &[usize]) -> usize {
+        let mut levels: Vec<usize> = lits.iter().map(|l| self.level[l >> 1]).collect();
+        levels.sort();
+        levels.dedup();
+        levels.len()
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        for i in (self.trail_lim[level]..self.trail.len()).rev() {
+            let lit = self.trail[i];
+            let var = lit >> 1;
+            self.assigns[var] = None;
+            self.reason[var] = None;
+            self.polarity[var] = lit & 1 == 0;
+            self.order.insert(var, &self.activity);
+        }
+        self.trail.truncate(self.trail_lim[level]);
+        self.trail_lim.truncate(level);
+        self.qhead = self.trail.len();
+    }
+
+    fn pick_branch(&mut self) -> Option<usize> {
+        if self.config.random_freq > 0.0 && self.rng.next_f64() < self.config.random_freq {
+            let var = 1 + self.rng.below(self.nvar);
+            if self.assigns[var].is_none() {
+                return Some(var);
+            }
+        }
+        while let Some(var) = self.order.pop(&self.activity) {
+            if self.assigns[var].is_none() {
+                return Some(var);
+            }
+        }
+        None
+    }
+
+    /// Delete the less useful half of the learned clauses: the ones spanning the most decision
+    /// levels. Clauses with two levels or fewer, and reasons of current assignments, are kept.
+    fn reduce(&mut self) {
+        let mut candidates: Vec<usize> = (0..self.clauses.len())
+                                             .filter(|id| {
+                                                 let c = &self.clauses[*id];
+                                                 c.learnt && !c.deleted && c.lbd > 2 &&
+                                                 self.reason[c.lits[0] >> 1] != Some(*id)
+                                             })
+                                             .collect();
+        candidates.sort_by_key(|id| std::cmp::Reverse(self.clauses[*id].lbd));
+        for &id in &candidates[..candidates.len() / 2] {
+            self.clauses[id].deleted = true;
+            self.clauses[id].lits = Vec::new();
+            self.learnts -= 1;
+        }
+        self.max_learnts *= 1.1;
+    }
+
+    /// Vivify clauses of three literals or more, round robin, until `budget` runs out. Only called
+    /// at decision level 0.
+    fn vivify(&mut self, budget: Duration) {
+        let start = Instant::now();
+        // Vivifying shouldn't change which values the search tries first
+        let polarity = self.polarity.clone();
+        for _ in 0..self.clauses.len() {
+            if !self.ok || start.elapsed() >= budget {
+                break;
+            }
+            let id = self.vivify_next % self.clauses.len();
+            self.vivify_next = id + 1;
+            let clause = &self.clauses[id];
+            if clause.deleted || clause.lits.len() < 3 ||
+               clause.lits.iter().any(|l| self.value(*l) == Some(true)) {
+                continue;
+            }
+            let (lits, learnt, lbd) = (clause.lits.clone(), clause.learnt, clause.lbd);
+
+            // Detach the clause, so that it doesn't take part in its own propagation
+            self.clauses[id].deleted = true;
+            self.clauses[id].lits = Vec::new();
+            if learnt {
+                self.learnts -= 1;
+            }
+            let mut kept = Vec::new();
+            for &lit in &lits {
+                match self.value(lit) {
+                    // The negations so far imply `lit`
+                    Some(true) => {
+                        kept.push(lit);
+                        break;
+                    }
+                    Some(false) => {}
+                    None => {
+                        kept.push(lit);
+                        self.trail_lim.push(self.trail.len());
+                        self.enqueue(lit ^ 1, None);
+                        if self.propagate().is_some() {
+                            break;
+                        }
+                    }
+                }
+            }
+            self.cancel_until(0);
+            if kept.len() == lits.len() {
+                // Nothing to remove: put the clause back in its own slot, with the watches
+                // propagation hasn't dropped yet replaced
+                for &lit in &lits[..2] {
+                    self.watches[lit].retain(|w| *w != id);
+                    self.watches[lit].push(id);
+                }
+                if learnt {
+                    self.learnts += 1;
+                }
+                self.clauses[id].lits = lits;
+                self.clauses[id].deleted = false;
+                continue;
+            }
+            self.stats.vivified += 1;
+            if kept.len() >= 2 {
+                let lbd = lbd.min(kept.len());
+                self.attach(kept, learnt, lbd);
+            } else {
+                self.add_internal(kept, learnt);
+            }
+        }
+        self.polarity = polarity;
+    }
+
+    /// Take in the clauses other solvers have shared. Only called at decision level 0.
+    fn import(&mut self) {
+        let shared = match self.exchange {
+            Some((ref exchange, id, ref mut cursor)) => exchange.import(id, cursor),
+            None => return,
+        };
+        for clause in shared {
+            self.add_internal(clause.iter().map(|l| from_dimacs(*l)).collect(), true);
+        }
+    }
+
+    fn export(&self, lits: &[usize]) {
+        if let Some((ref exchange, id, _)) = self.exchange {
+            if lits.len() <= exchange.max_len() {
+                let clause: Vec<i32> = lits.iter().map(|l| to_dimacs(*l)).collect();
+                exchange.export(id, &clause);
+            }
+        }
+    }
+
+    /// Search until `conflicts` conflicts have happened, then return `None` to restart.
+    fn search(&mut self, conflicts: u64, assumptions: &[usize]) -> Option<Status> {
+        let mut count = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.stats.conflicts += 1;
+                count += 1;
+                if self.decision_level() == 0 {
+                    self.ok = false;
+                    return Some(Status::Unsatisfiable);
+                }
+                let (learnt, backjump) = self.analyze(conflict);
+                self.cancel_until(backjump);
+                self.export(&learnt);
+                self.stats.learned += 1;
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let lbd = self.lbd(&learnt);
+                    let first = learnt[0];
+                    let id = self.attach(learnt, true, lbd);
+                    self.enqueue(first, Some(id));
+                }
+                if self.config.heuristic == Heuristic::Vsids {
+                    self.var_inc /= self.config.decay;
+                }
+                continue;
+            }
+
+            if self.stopped() {
+                return Some(Status::Unknown);
+            }
+            if count >= conflicts {
+                self.cancel_until(0);
+                return None;
+            }
+            if self.learnts as f64 >= self.max_learnts + self.trail.len() as f64 {
+                self.reduce();
+            }
+
+            let mut next = None;
+            while self.decision_level() < assumptions.len() {
+                let lit = assumptions[self.decision_level()];
+                match self.value(lit) {
+                    // Already true: open an empty level so the levels line up
+                    Some(true) => self.trail_lim.push(self.trail.len()),
+                    Some(false) => return Some(Status::Unsatisfiable),
+                    None => {
+                        next = Some(lit);
+                        break;
+                    }
+                }
+            }
+            let lit = match next {
+                Some(lit) => lit,
+                None => {
+                    match self.pick_branch() {
+                        Some(var) => if self.polarity[var] { var << 1 } else { var << 1 | 1 },
+                        None => return Some(Status::Satisfiable),
+                    }
+                }
+            };
+            self.stats.decisions += 1;
+            self.trail_lim.push(self.trail.len());
+            self.enqueue(lit, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use super::*;
+
+    fn solve_file(name: &str, config: Config) -> (CNF, Status, Assignment) {
+        let cnf = parse_file(name);
+        let mut solver = Solver::new(&cnf, config);
+        let status = solver.solve();
+        let model = solver.model().clone();
+        (cnf, status, model)
+    }
+
+    #[test]
+    fn luby_sequence() {
+        let seq: Vec<u64> = (0..15).map(luby).collect();
+        assert_eq!(seq, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn solves_satisfiable_files() {
+        let names = ["tests/uf250-01.cnf", "tests/med_64_254.cnf", "tests/subsets100.cnf"];
+        for name in names.iter() {
+            let (cnf, status, model) = solve_file(name, Config::default());
+            assert_eq!(status, Status::Satisfiable);
+            assert!(satisfies(&cnf, &model));
+        }
+    }
+
+    #[test]
+    fn refutes_unsatisfiable_file() {
+        for restarts in [Restarts::Never,
+                         Restarts::Luby(50),
+                         Restarts::Geometric {
+                             first: 100,
+                             factor: 1.5,
+                         }]
+                            .iter() {
+            let config = Config {
+                restarts: *restarts,
+                ..Config::default()
+            };
+            let (_, status, _) = solve_file("tests/dubois29_unsat.cnf", config);
+            assert_eq!(status, Status::Unsatisfiable);
+        }
+        let names = ["tests/long_1318_3668.cnf",
+                     "tests/phole/hole7.cnf",
+                     "tests/pret/pret60_25.cnf"];
+        for name in names.iter() {
+            let (_, status, _) = solve_file(name, Config::default());
+            assert_eq!(status, Status::Unsatisfiable);
+        }
+    }
+
+    #[test]
+    fn jw_order_and_random_decisions() {
+        let config = Config {
+            heuristic: Heuristic::Jw,
+            random_freq: 0.05,
+            ..Config::default()
+        };
+        let (cnf, status, model) = solve_file("tests/medium.cnf", config);
+        assert_eq!(status, Status::Satisfiable);
+        assert!(satisfies(&cnf, &model));
+    }
+
+    #[test]
+    fn assumptions() {
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![-2, 3]);
+        let mut solver = Solver::new(&cnf, Config::default());
+        assert_eq!(solver.solve_with(&[1, -3]), Status::Unsatisfiable);
+        // Only unsatisfiable under those assumptions
+        assert_eq!(solver.solve_with(&[1]), Status::Satisfiable);
+        assert_eq!(solver.model(), &vec![true, true, true]);
+        assert!(solver.add_clause(&[-3]));
+        assert_eq!(solver.solve(), Status::Satisfiable);
+        assert!(!solver.add_clause(&[1]));
+        assert_eq!(solver.solve(), Status::Unsatisfiable);
+    }
+
+    #[test]
+    fn vivification() {
+        let mut cnf = CNF::new(4, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![1, 2, 3, 4]);
+        let mut solver = Solver::new(&cnf, Config::default());
+        solver.vivify(Duration::from_secs(1));
+        // -1 implies 2, so (1 2 3 4) shrinks to (1 2)
+        assert_eq!(solver.stats.vivified, 1);
+        assert!(solver.clauses[1].deleted);
+        assert_eq!(solver.clauses[2].lits, vec![1 << 1, 2 << 1]);
+
+        // Clauses vivification can't shorten stay in their slots
+        let mut cnf = CNF::new(4, 0);
+        cnf.add_clause(vec![1, 2, 3]);
+        cnf.add_clause(vec![-1, 3, 4]);
+        let mut solver = Solver::new(&cnf, Config::default());
+        solver.vivify(Duration::from_secs(1));
+        solver.vivify(Duration::from_secs(1));
+        assert_eq!(solver.stats.vivified, 0);
+        assert_eq!(solver.clauses.len(), 2);
+        assert!(solver.clauses.iter().all(|c| !c.deleted));
+        assert_eq!(solver.solve(), Status::Satisfiable);
+
+        let config = Config {
+            restarts: Restarts::Luby(50),
+            vivify: Some(Duration::from_millis(2)),
+            ..Config::default()
+        };
+        let (cnf, status, model) = solve_file("tests/uf250-01.cnf", config.clone());
+        assert_eq!(status, Status::Satisfiable);
+        assert!(satisfies(&cnf, &model));
+        for name in ["tests/long_1318_3668.cnf", "tests/phole/hole7.cnf"].iter() {
+            let (_, status, _) = solve_file(name, config.clone());
+            assert_eq!(status, Status::Unsatisfiable);
+        }
+    }
+
+    #[test]
+    fn imported_clauses_can_be_reduced() {
+        let mut cnf = CNF::new(4, 0);
+        cnf.add_clause(vec![1, 2]);
+        let exchange = Arc::new(Exchange::new(10, 8));
+        let mut solver = Solver::new(&cnf, Config::default());
+        solver.share(exchange.clone(), 0);
+        exchange.export(1, &[1, -2, 3, 4]);
+        solver.import();
+        let imported = solver.clauses.last().unwrap();
+        assert!(imported.learnt);
+        assert_eq!(imported.lbd, 4);
+    }
+
+    #[test]
+    fn follows_phases() {
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![1, 2, 3]);
+        let mut solver = Solver::new(&cnf, Config::default());
+        solver.follow_phases(Arc::new(Mutex::new(vec![false, false, true])));
+        assert_eq!(solver.solve(), Status::Satisfiable);
+        assert_eq!(solver.model(), &vec![false, false, true]);
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let cnf = parse_file("tests/dubois29_unsat.cnf");
+        let mut solver = Solver::new(&cnf, Config::default());
+        solver.set_stop(Arc::new(AtomicBool::new(true)));
+        assert_eq!(solver.solve(), Status::Unknown);
+    }
+}
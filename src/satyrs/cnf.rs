@@ -8,6 +8,7 @@ use std::collections::HashSet;
 use std::vec::Vec;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::str::FromStr;
 use std::fs::File;
 
 use std::clone::Clone;
@@ -30,6 +31,26 @@ macro_rules! create_tempfile {
     }};
 }
 
+/// Helpers for the tests of any module that solves the formulas under `tests/`.
+#[cfg(test)]
+pub mod test_util {
+    use std::fs::File;
+
+    use super::{Assignment, CNF, parse_dimacs_file};
+
+    /// Parse one of the DIMACS files under `tests/`.
+    pub fn parse_file(name: &str) -> CNF {
+        parse_dimacs_file(File::open(name).unwrap()).unwrap()
+    }
+
+    /// Does `assn`, which has a value for every variable, satisfy every clause of `cnf`?
+    pub fn satisfies(cnf: &CNF, assn: &Assignment) -> bool {
+        cnf.clauses
+           .values()
+           .all(|c| c.iter().any(|l| assn[((l >> 1) - 1) as usize] == (l & 1 == 0)))
+    }
+}
+
 /// CNF will house all clauses, key is int so that we can use occurrences
 /// Occurrences tracks which clauses literals are used in for simplifications
 /// in DPLL.
@@ -40,6 +61,8 @@ pub struct CNF {
     pub clauses: HashMap<i32, HashSet<i32>>,
     pub occurrences: HashMap<i32, HashSet<i32>>,
     pub units: HashSet<i32>,
+    /// ID for the next clause added. Clauses can be removed, so this isn't `clauses.len()`.
+    next_id: i32,
 }
 
 impl CNF {
@@ -53,6 +76,7 @@ impl CNF {
             clauses: HashMap::new(),
             occurrences: HashMap::new(),
             units: HashSet::new(),
+            next_id: 0,
         }
     }
 
@@ -85,12 +109,76 @@ impl CNF {
         self._add_clause(hs);
     }
 
+    /// Remove `lit` from the clause with id `id`, if it is there, keeping the occurrences and the
+    /// unit clauses up to date.
+    pub fn strengthen(&mut self, id: i32, lit: i32) {
+        if let Some(clause) = self.clauses.get_mut(&id) {
+            if clause.remove(&lit) {
+                if let Some(occ) = self.occurrences.get_mut(&lit) {
+                    occ.remove(&id);
+                    if occ.is_empty() {
+                        self.occurrences.remove(&lit);
+                    }
+                }
+                if clause.len() == 1 {
+                    self.units.insert(id);
+                }
+            }
+        }
+    }
+
+    /// Add a clause of literals in the internal representation, and return its ID.
+    pub fn insert_clause(&mut self, clause: HashSet<i32>) -> i32 {
+        self.nclause += 1;
+        self._add_clause(clause)
+    }
+
+    /// Remove the clause with id `id`, keeping the occurrences and the unit clauses up to date.
+    pub fn remove_clause(&mut self, id: i32) -> Option<HashSet<i32>> {
+        let clause = self.clauses.remove(&id)?;
+        for lit in &clause {
+            if let Some(occ) = self.occurrences.get_mut(lit) {
+                occ.remove(&id);
+                if occ.is_empty() {
+                    self.occurrences.remove(lit);
+                }
+            }
+        }
+        self.units.remove(&id);
+        self.nclause -= 1;
+        Some(clause)
+    }
+
+    /// The clauses in order of ID, each with its literals (in the internal representation)
+    /// sorted. Iterating over `clauses` gives a different order on every run, so solvers that
+    /// copy the clauses into their own structures take them from here: the same formula, seed
+    /// and configuration then always give the same run.
+    pub fn sorted_clauses(&self) -> Vec<Vec<i32>> {
+        let mut ids: Vec<&i32> = self.clauses.keys().collect();
+        ids.sort();
+        ids.into_iter()
+           .map(|id| {
+               let mut clause: Vec<i32> = self.clauses[id].iter().cloned().collect();
+               clause.sort();
+               clause
+           })
+           .collect()
+    }
+
+    /// Allocate a fresh variable and return its (DIMACS) number. Used by the encoders to
+    /// introduce auxiliary variables without knowing the final variable count up front.
+    pub fn new_var(&mut self) -> i32 {
+        self.nvar += 1;
+        self.nvar
+    }
+
     /// Add a clause, return the ID of the inserted clause
     /// Right now, this isn't public; api is odd as we have an odd representation of literals.
     /// TODO: Mask this with public function?
     fn _add_clause(&mut self, clause: HashSet<i32>) -> i32 {
         assert!(clause.len() > 0);
-        let id: i32 = self.clauses.len() as i32;
+        let id: i32 = self.next_id;
+        self.next_id += 1;
         if clause.len() == 1 {
             self.units.insert(id);
         }
@@ -180,6 +268,7 @@ impl Clone for CNF {
             clauses: self.clauses.clone(),
             occurrences: self.occurrences.clone(),
             units: self.units.clone(),
+            next_id: self.next_id,
         }
     }
 }
@@ -297,105 +386,359 @@ impl Display for PartialAssignment {
 
 // Begin Parsing
 
+/// The quantifier of a QDIMACS block. Solving is up to `satyrs::qbf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantifier {
+    Exists,
+    Forall,
+}
+
+/// A QDIMACS quantifier prefix: blocks of variables, outermost first.
+pub type Prefix = Vec<(Quantifier, Vec<i32>)>;
+
+/// What parsing dropped from a DIMACS file without changing the formula it describes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseReport {
+    /// Clauses containing both a literal and its negation, which every assignment satisfies.
+    pub tautologies: usize,
+    /// Literals repeated within a clause.
+    pub duplicate_literals: usize,
+    /// Clauses with the same literals as an earlier clause, in any order.
+    pub duplicate_clauses: usize,
+    /// Departures from strict DIMACS that were accepted anyway, by line. Only collected in strict
+    /// mode.
+    pub warnings: Vec<String>,
+}
+
+/// What went wrong in a DIMACS file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DimacsErrorKind {
+    /// The file could not be read; the token holds the reason.
+    Io,
+    NoProblemStatement,
+    InvalidProblemStatement,
+    InvalidVariableCount,
+    InvalidClauseCount,
+    UnknownStatement,
+    DuplicateProblemStatement,
+    QuantifierAfterClauses,
+    InvalidLiteral,
+    VariableOutOfRange,
+    EmptyClause,
+    TooManyClauses,
+    TooFewClauses,
+}
+
+impl DimacsErrorKind {
+    pub fn message(&self) -> &'static str {
+        match *self {
+            DimacsErrorKind::Io => "could not read file",
+            DimacsErrorKind::NoProblemStatement => "no problem statement found",
+            DimacsErrorKind::InvalidProblemStatement => "invalid problem statement",
+            DimacsErrorKind::InvalidVariableCount => "invalid number of variables",
+            DimacsErrorKind::InvalidClauseCount => "invalid number of clauses",
+            DimacsErrorKind::UnknownStatement => "unknown statement beginning",
+            DimacsErrorKind::DuplicateProblemStatement => "duplicate problem statement",
+            DimacsErrorKind::QuantifierAfterClauses => "quantifier block after clauses",
+            DimacsErrorKind::InvalidLiteral => "invalid literal",
+            DimacsErrorKind::VariableOutOfRange => "variable out of range",
+            DimacsErrorKind::EmptyClause => "empty clause in file",
+            DimacsErrorKind::TooManyClauses => "too many clauses in file",
+            DimacsErrorKind::TooFewClauses => "too few clauses in file",
+        }
+    }
+}
+
+/// An error in a DIMACS file, with where it is. Lines and columns count from 1; a column of 0
+/// means the whole line, and errors found at the end of the file are on its last line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DimacsError {
+    pub kind: DimacsErrorKind,
+    pub line: usize,
+    pub column: usize,
+    /// The offending word, or empty if there is none.
+    pub token: String,
+}
+
+impl DimacsError {
+    fn new(kind: DimacsErrorKind, line: usize, column: usize, token: &str) -> DimacsError {
+        DimacsError {
+            kind,
+            line,
+            column,
+            token: token.to_string(),
+        }
+    }
+}
+
+impl Display for DimacsError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "line {}", self.line)?;
+        if self.column > 0 {
+            write!(f, ", column {}", self.column)?;
+        }
+        write!(f, ": {}", self.kind.message())?;
+        if !self.token.is_empty() {
+            write!(f, " `{}`", self.token)?;
+        }
+        Ok(())
+    }
+}
+
+impl ::std::error::Error for DimacsError {}
+
+/// The whitespace-separated words of `line`, each with its column.
+fn words_with_columns(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                words.push((s + 1, &line[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s + 1, &line[s..]));
+    }
+    words
+}
+
 // TODO: rename to from_dimacs or somehow isolate parsing
-fn parse_dimacs(reader: &mut BufReader<File>) -> Result<CNF, &'static str> {
+/// Parse DIMACS from `reader`. If `prefix` is given, QDIMACS quantifier blocks (`a`/`e` lines
+/// between the problem statement and the first clause) are accepted and collected into it.
+/// Tautologies and duplicate clauses are left out, and reported along with duplicate literals.
+/// Clauses may span lines or share them, and a `%` line ends the clauses; in `strict` mode each
+/// of these is reported as a warning.
+fn parse_dimacs<R: BufRead>(reader: R,
+                            mut prefix: Option<&mut Prefix>,
+                            strict: bool)
+                            -> Result<(CNF, ParseReport), DimacsError> {
     let mut line_iterator = reader.lines();
+    let mut line_number = 0;
+    let io_error = |line: usize, e: ::std::io::Error| {
+        DimacsError::new(DimacsErrorKind::Io, line, 0, &e.to_string())
+    };
 
     let mut nvar: i32 = -1;
     let mut nclause: i32 = -1;
     // DIMACS file must have a problem statement before other lines.
     // This first loop searches for the problem statement.
     for line in &mut line_iterator {
-        let line = line.expect("could not read file");  // Unwrap result
-        let words: Vec<&str> = line.split_whitespace().collect();
-        if words.len() == 0 {
+        line_number += 1;
+        let line = line.map_err(|e| io_error(line_number, e))?;
+        let words = words_with_columns(&line);
+        if words.is_empty() {
             continue;
         } // Ignore empty lines
-        match words[0] {
+        let error = |kind, (column, word): (usize, &str)| {
+            DimacsError::new(kind, line_number, column, word)
+        };
+        match words[0].1 {
             "c" => {}
             "p" => {
                 // Problem statement
                 // Must have format "p cnf nvar nclause"
-                if words.len() != 4 || words[1] != "cnf" {
-                    return Err("invalid problem statement");
+                if words.len() != 4 || words[1].1 != "cnf" {
+                    return Err(error(DimacsErrorKind::InvalidProblemStatement, (1, "")));
                 }
-                nvar = words[2]
-                           .parse()
-                           .expect(&format!("invalid number of variables {}", words[2]));
-                nclause = words[3]
-                              .parse()
-                              .expect(&format!("invalid number of clauses {}", words[3]));
+                nvar = match words[2].1.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(error(DimacsErrorKind::InvalidVariableCount, words[2])),
+                };
+                nclause = match words[3].1.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(error(DimacsErrorKind::InvalidClauseCount, words[3])),
+                };
                 break;
             }
-            // TODO: Add words[0] to this error message
             _ => {
-                return Err("unknown statement beginning");
+                return Err(error(DimacsErrorKind::UnknownStatement, words[0]));
             }
         }
     }
     // Then nvar, nclause were never initialized
     if nvar == -1 || nclause == -1 {
-        return Err("no problem statement found");
-    }
-    // TODO: Different errors for different descriptions (hence why I've split up this if
-    // statement)
-    if nvar == 0 || nclause == 0 {
-        return Err("invalid number of literals in problem");
+        return Err(DimacsError::new(DimacsErrorKind::NoProblemStatement, line_number, 0, ""));
     }
 
-    // Initialize CNF and parse the rest of the file
+    // Initialize CNF and parse the rest of the file as one stream of literals, in which every 0
+    // ends a clause, wherever the lines break
     let mut cnf = CNF::new(nvar, nclause);
     let mut clauses_read: i32 = 0;
+    let mut report = ParseReport::default();
+    let mut seen: HashSet<Vec<i32>> = HashSet::new();
+    let mut clause: Vec<i32> = Vec::new();
     for line in &mut line_iterator {
-        let line = line.unwrap();
-        let words: Vec<&str> = line.split_whitespace().collect();
-        if words.len() == 0 {
+        line_number += 1;
+        let line = line.map_err(|e| io_error(line_number, e))?;
+        let words = words_with_columns(&line);
+        if words.is_empty() {
             continue;
         }
-        match words[0] {
+        let error = |kind, (column, word): (usize, &str)| {
+            DimacsError::new(kind, line_number, column, word)
+        };
+        match words[0].1 {
             "c" => {}
             "p" => {
-                return Err("duplicate problem statement");
+                return Err(error(DimacsErrorKind::DuplicateProblemStatement, words[0]));
+            }
+            "%" => {
+                // SATLIB files end the clauses with a `%` line, followed by a stray 0
+                if strict {
+                    report.warnings.push(format!("line {}: clauses ended by %", line_number));
+                }
+                break;
+            }
+            "a" | "e" if prefix.is_some() => {
+                if clauses_read > 0 || !clause.is_empty() {
+                    return Err(error(DimacsErrorKind::QuantifierAfterClauses, words[0]));
+                }
+                let quantifier = if words[0].1 == "a" {
+                    Quantifier::Forall
+                } else {
+                    Quantifier::Exists
+                };
+                let mut block = Vec::new();
+                for &word in &words[1..] {
+                    let v: i32 = match word.1.parse() {
+                        Ok(v) if v >= 0 => v,
+                        _ => return Err(error(DimacsErrorKind::InvalidLiteral, word)),
+                    };
+                    if v == 0 {
+                        break;
+                    }
+                    if v > nvar {
+                        return Err(error(DimacsErrorKind::VariableOutOfRange, word));
+                    }
+                    block.push(v);
+                }
+                if let Some(ref mut prefix) = prefix {
+                    prefix.push((quantifier, block));
+                }
             }
             _ => {
-                clauses_read = clauses_read + 1;
-                if clauses_read > nclause {
-                    return Err("too many clauses in file");
+                let mut ended = 0;
+                for &word in &words {
+                    let n: i32 = match word.1.parse() {
+                        Ok(n) => n,
+                        Err(_) => return Err(error(DimacsErrorKind::InvalidLiteral, word)),
+                    };
+                    if n != 0 {
+                        // `abs` would overflow on i32::MIN
+                        if n.unsigned_abs() > nvar as u32 {
+                            return Err(error(DimacsErrorKind::VariableOutOfRange, word));
+                        }
+                        clause.push(n);
+                        continue;
+                    }
+                    if clause.is_empty() {
+                        return Err(error(DimacsErrorKind::EmptyClause, word));
+                    }
+                    clauses_read += 1;
+                    if clauses_read > nclause {
+                        return Err(error(DimacsErrorKind::TooManyClauses, word));
+                    }
+                    add_parsed_clause(&mut cnf, &clause, &mut report, &mut seen);
+                    clause.clear();
+                    ended += 1;
+                }
+                if strict && ended > 1 {
+                    report.warnings.push(format!("line {}: {} clauses on one line",
+                                                 line_number,
+                                                 ended));
+                }
+                if strict && !clause.is_empty() {
+                    report.warnings
+                          .push(format!("line {}: clause continues on the next line", line_number));
                 }
-                let tokens: HashSet<i32> = words.iter()
-                                                .filter_map(|s| {
-                                                    let n = s.parse::<i32>().unwrap();
-                                                    if n == 0 {
-                                                        return None;
-                                                    }
-                                                    // FIXME: This should return an error instead of
-                                                    // panicking, but I can't return an error
-                                                    // in a closure
-                                                    if n > nvar {
-                                                        panic!("variable out of range: {}", n);
-                                                    }
-                                                    Some(if n < 0 {
-                                                        (-n) << 1 | 1
-                                                    } else {
-                                                        n << 1
-                                                    })
-                                                })
-                                                .collect();
-                cnf._add_clause(tokens);
             }
         }
     }
+    // Accept a last clause without its 0
+    if !clause.is_empty() {
+        if strict {
+            report.warnings.push("last clause not ended by 0".to_string());
+        }
+        clauses_read += 1;
+        if clauses_read > nclause {
+            return Err(DimacsError::new(DimacsErrorKind::TooManyClauses, line_number, 0, ""));
+        }
+        add_parsed_clause(&mut cnf, &clause, &mut report, &mut seen);
+    }
     // Double check that the number of clauses read is equal
     if clauses_read != nclause {
-        return Err("too few clauses in file");
+        return Err(DimacsError::new(DimacsErrorKind::TooFewClauses, line_number, 0, ""));
     }
-    Ok(cnf)
+    Ok((cnf, report))
+}
+
+/// Add a clause of DIMACS literals read from a file, unless it is a tautology or repeats an
+/// earlier clause.
+fn add_parsed_clause(cnf: &mut CNF,
+                     clause: &[i32],
+                     report: &mut ParseReport,
+                     seen: &mut HashSet<Vec<i32>>) {
+    let tokens: HashSet<i32> = clause.iter()
+                                     .map(|&n| if n < 0 { (-n) << 1 | 1 } else { n << 1 })
+                                     .collect();
+    report.duplicate_literals += clause.len() - tokens.len();
+    let mut sorted: Vec<i32> = tokens.iter().cloned().collect();
+    sorted.sort();
+    if sorted.windows(2).any(|w| w[0] ^ 1 == w[1]) {
+        report.tautologies += 1;
+        cnf.nclause -= 1;
+    } else if !seen.insert(sorted) {
+        report.duplicate_clauses += 1;
+        cnf.nclause -= 1;
+    } else {
+        cnf._add_clause(tokens);
+    }
+}
+
+pub fn parse_dimacs_file(f: File) -> Result<CNF, DimacsError> {
+    parse_dimacs_file_with_report(f, false).map(|(cnf, _)| cnf)
 }
 
-pub fn parse_dimacs_file(f: File) -> Result<CNF, &'static str> {
-    // Read the file
-    let mut reader = BufReader::new(f);
-    parse_dimacs(&mut reader)
+/// Parse a DIMACS file, and report what was dropped while loading it, and in `strict` mode
+/// where the file departs from the format.
+pub fn parse_dimacs_file_with_report(f: File,
+                                     strict: bool)
+                                     -> Result<(CNF, ParseReport), DimacsError> {
+    parse_dimacs_with_report(BufReader::new(f), strict)
+}
+
+/// Parse DIMACS from any buffered source, such as a pipe or `io::stdin().lock()`, with the same
+/// report as `parse_dimacs_file_with_report`.
+pub fn parse_dimacs_with_report<R: BufRead>(reader: R,
+                                            strict: bool)
+                                            -> Result<(CNF, ParseReport), DimacsError> {
+    parse_dimacs(reader, None, strict)
+}
+
+/// Parse a QDIMACS file into its matrix and its quantifier prefix, outermost block first.
+pub fn parse_qdimacs_file(f: File) -> Result<(CNF, Prefix), DimacsError> {
+    parse_qdimacs(BufReader::new(f))
+}
+
+/// Parse QDIMACS from any buffered source, as `parse_qdimacs_file` does a file.
+pub fn parse_qdimacs<R: BufRead>(reader: R) -> Result<(CNF, Prefix), DimacsError> {
+    let mut prefix = Vec::new();
+    let (cnf, _) = parse_dimacs(reader, Some(&mut prefix), false)?;
+    Ok((cnf, prefix))
+}
+
+/// Parse a formula written out in DIMACS, as `"p cnf 2 1\n1 -2 0".parse::<CNF>()`.
+impl FromStr for CNF {
+    type Err = DimacsError;
+
+    fn from_str(s: &str) -> Result<CNF, DimacsError> {
+        parse_dimacs(s.as_bytes(), None, false).map(|(cnf, _)| cnf)
+    }
 }
 
 pub fn format_output(assn: &Assignment) -> String {
@@ -424,44 +767,44 @@ mod tests {
     use std::io::SeekFrom;
     use std::collections::HashSet;
 
-    use super::parse_dimacs_file;
+    use super::{CNF, DimacsErrorKind, ParseReport, parse_dimacs_file,
+                parse_dimacs_file_with_report, parse_dimacs_with_report};
     // use super::zeroth;
 
     #[test]
-    #[should_panic(expected = "out of range")]
     fn variable_out_of_range() {
         let tmpfile = create_tempfile!("
             p cnf 2 3
             1 2 0
             4 1 0
         ");
-        let _ = parse_dimacs_file(tmpfile).unwrap();
+        let error = parse_dimacs_file(tmpfile).unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::VariableOutOfRange);
     }
 
     #[test]
-    #[should_panic(expected = "invalid number")]
     fn invalid_nvar() {
         let tmpfile = create_tempfile!("
             p cnf gd 3
             1 2 0
             4 1 0
         ");
-        let _ = parse_dimacs_file(tmpfile).unwrap();
+        let error = parse_dimacs_file(tmpfile).unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::InvalidVariableCount);
     }
 
     #[test]
-    #[should_panic(expected = "invalid number")]
     fn invalid_nclause() {
         let tmpfile = create_tempfile!("
             p cnf 2 gdd
             1 2 0
             4 1 0
         ");
-        let _ = parse_dimacs_file(tmpfile).unwrap();
+        let error = parse_dimacs_file(tmpfile).unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::InvalidClauseCount);
     }
 
     #[test]
-    #[should_panic(expected = "too many")]
     fn too_many_clauses() {
         let tmpfile = create_tempfile!("
             p cnf 5 5
@@ -472,11 +815,11 @@ mod tests {
             1 3 0
             2 4 0
         ");
-        let _ = parse_dimacs_file(tmpfile).unwrap();
+        let error = parse_dimacs_file(tmpfile).unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::TooManyClauses);
     }
 
     #[test]
-    #[should_panic(expected = "too few")]
     fn too_little_clauses() {
         let tmpfile = create_tempfile!("
             p cnf 5 5
@@ -484,7 +827,8 @@ mod tests {
             2 3 0
             3 4 0
         ");
-        let _ = parse_dimacs_file(tmpfile).unwrap();
+        let error = parse_dimacs_file(tmpfile).unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::TooFewClauses);
     }
 
     #[test]
@@ -516,7 +860,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "duplicate problem")]
     fn duplicate_problem_statement() {
         let tmpfile = create_tempfile!("
             p cnf 3 3
@@ -525,11 +868,11 @@ mod tests {
             2 3 0
             1 -3 0
         ");
-        let _ = parse_dimacs_file(tmpfile).unwrap();
+        let error = parse_dimacs_file(tmpfile).unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::DuplicateProblemStatement);
     }
 
     #[test]
-    #[should_panic(expected = "duplicate problem")]
     fn duplicate_problem_statement_2() {
         let tmpfile = create_tempfile!("
             p cnf 3 3
@@ -538,7 +881,8 @@ mod tests {
             2 3 0
             1 -3 0
         ");
-        let _ = parse_dimacs_file(tmpfile).unwrap();
+        let error = parse_dimacs_file(tmpfile).unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::DuplicateProblemStatement);
     }
 
     #[test]
@@ -590,16 +934,11 @@ mod tests {
     /// unit clause can still be unit propagated and will fail gracefully
     #[test]
     fn unit_propagate_works_special() {
-        let tmpfile = create_tempfile!("
-            p cnf 2 6
-            1 0
-            1 0
-            2 0
-            2 0
-            2 0
-            2 0
-        ");
-        let mut cnf = parse_dimacs_file(tmpfile).unwrap();
+        // Parsing would merge duplicate clauses, so add them directly
+        let mut cnf = CNF::new(2, 0);
+        for lit in [1, 1, 2, 2, 2, 2].iter() {
+            cnf.add_clause(vec![*lit]);
+        }
         // There are six clauses and all of them are units. One iteration of dpll will attempt to
         // remove all of these clauses. Even though unit_propagate(0) and unit_propagate(2) will
         // remove all of the clauses, the other unit_propagates should not panic.
@@ -614,6 +953,141 @@ mod tests {
         assert_eq!(cnf.units.len(), 0);
     }
 
+    #[test]
+    fn drops_tautologies_and_duplicates() {
+        let text = "
+            p cnf 3 6
+            1 0
+            1 0
+            2 1 -3 2 0
+            1 2 -3 0
+            3 -1 2 1 0
+            -2 -3 -2 0
+        ";
+        let (cnf, report) = parse_dimacs_with_report(text.as_bytes(), false).unwrap();
+        assert_eq!(report,
+                   ParseReport {
+                       tautologies: 1,
+                       duplicate_literals: 2,
+                       duplicate_clauses: 2,
+                       warnings: Vec::new(),
+                   });
+        assert_eq!(cnf.clauses.len(), 3);
+        assert_eq!(cnf.nclause, 3);
+        assert_eq!(cnf.units.len(), 1);
+    }
+
+    #[test]
+    fn clauses_split_on_zeros() {
+        let text = "
+            p cnf 5 4
+            1 2
+            -3 0 4 5 0
+            c comment
+            -1 0 -2
+            -4 0
+            %
+            0
+        ";
+        let (cnf, report) = parse_dimacs_with_report(text.as_bytes(), true).unwrap();
+        assert_eq!(cnf.clauses.len(), 4);
+        assert_eq!(cnf.units.len(), 1);
+        let lines: Vec<&str> = report.warnings
+                                     .iter()
+                                     .map(|w| w.split(':').next().unwrap())
+                                     .collect();
+        assert_eq!(lines, vec!["line 3", "line 4", "line 6", "line 8"]);
+    }
+
+    #[test]
+    fn last_clause_without_zero() {
+        let text = "
+            p cnf 3 2
+            1 -2 0
+            2 3
+        ";
+        let (cnf, report) = parse_dimacs_with_report(text.as_bytes(), true).unwrap();
+        assert_eq!(cnf.clauses.len(), 2);
+        assert_eq!(report.warnings.len(), 2);
+    }
+
+    #[test]
+    fn satlib_files() {
+        // hole9 breaks its last clause before the 0; uf250-01 is strict DIMACS
+        let expected = [("tests/uf250-01.cnf", vec![]),
+                        ("tests/phole/hole9.cnf",
+                         vec!["line 428: clause continues on the next line".to_string()])];
+        for &(name, ref warnings) in expected.iter() {
+            let (cnf, report) = parse_dimacs_file_with_report(File::open(name).unwrap(), true)
+                                    .unwrap();
+            assert_eq!(cnf.nclause as usize, cnf.clauses.len());
+            assert_eq!(&report.warnings, warnings);
+        }
+    }
+
+    #[test]
+    fn empty_clause() {
+        let error = "
+            p cnf 2 2
+            1 2 0 0
+        ".parse::<CNF>().unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::EmptyClause);
+    }
+
+    #[test]
+    fn errors_locate_token() {
+        // The literal is in column 15 of line 4, after 12 spaces of indentation and `1 `
+        let error = "
+            p cnf 3 2
+            1 2 0
+            1 x3 0
+        ".parse::<CNF>().unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::InvalidLiteral);
+        assert_eq!((error.line, error.column), (4, 15));
+        assert_eq!(error.token, "x3");
+        assert_eq!(error.to_string(), "line 4, column 15: invalid literal `x3`");
+
+        let error = "
+            p cnf 3 2
+            1 -4 0
+        ".parse::<CNF>().unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::VariableOutOfRange);
+        assert_eq!((error.line, error.column, &error.token[..]), (3, 15, "-4"));
+
+        let error = "
+            p cnf 3 -2
+        ".parse::<CNF>().unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::InvalidClauseCount);
+
+        let error = "
+            p cnf 3 2
+            1 2 0
+        ".parse::<CNF>().unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::TooFewClauses);
+        assert_eq!(error.to_string(), "line 4: too few clauses in file");
+    }
+
+    #[test]
+    fn most_negative_literal() {
+        let error = "p cnf 3 1\n1 -2147483648 0\n".parse::<CNF>().unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::VariableOutOfRange);
+        assert_eq!((error.line, error.column), (2, 3));
+    }
+
+    #[test]
+    fn parses_strings_and_readers() {
+        let cnf: CNF = "p cnf 3 2\n1 -2 0\n2 3 0\n".parse().unwrap();
+        assert_eq!(cnf.nvar, 3);
+        assert_eq!(cnf.clauses.len(), 2);
+        let error = "p cnf 3 2\n1 -2 0\n".parse::<CNF>().unwrap_err();
+        assert_eq!(error.kind, DimacsErrorKind::TooFewClauses);
+
+        let text = "p cnf 2 2\n1 2 0 -1 -2 0\n";
+        let (cnf, report) = parse_dimacs_with_report(text.as_bytes(), true).unwrap();
+        assert_eq!(cnf.clauses.len(), 2);
+        assert_eq!(report.warnings, vec!["line 2: 2 clauses on one line".to_string()]);
+    }
+
     #[test]
     fn remove_negation_adds_units() {
         let tmpfile = create_tempfile!("
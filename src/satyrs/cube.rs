@@ -0,0 +1,185 @@
+//! Cube-and-conquer. A lookahead cuber splits the formula into cubes, conjunctions of literals
+//! that between them cover every assignment, and the cubes are then solved in parallel as
+//! assumptions to incremental CDCL solvers.
+//!
+//! Cubes can also be written out in the iCNF format, `p inccnf` followed by the clauses and one
+//! `a <literals> 0` line per cube, for other incremental solvers to conquer.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use satyrs::cdcl::{self, Solver, Status};
+use satyrs::cnf::{Assignment, CNF};
+use satyrs::lookahead::{Decision, Lookahead};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Maximum number of decisions in a cube.
+    pub depth: usize,
+    /// Stop splitting once no more than this many variables are left in unsatisfied clauses.
+    pub cutoff: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            depth: 8,
+            cutoff: 0,
+        }
+    }
+}
+
+/// Split `cnf` into cubes of DIMACS literals. Branches refuted by lookahead are left out, so no
+/// cubes at all means the formula is unsatisfiable.
+pub fn cube(cnf: &CNF, config: Config) -> Vec<Vec<i32>> {
+    let mut lookahead = Lookahead::new(cnf);
+    let mut cubes = Vec::new();
+    split(&mut lookahead, config, &mut Vec::new(), &mut cubes);
+    cubes
+}
+
+fn split(lookahead: &mut Lookahead,
+         config: Config,
+         cube: &mut Vec<i32>,
+         cubes: &mut Vec<Vec<i32>>) {
+    if cube.len() >= config.depth || lookahead.free_vars().len() <= config.cutoff {
+        cubes.push(cube.clone());
+        return;
+    }
+    let mark = lookahead.mark();
+    match lookahead.decide() {
+        Decision::Refuted => {}
+        Decision::Satisfied => cubes.push(cube.clone()),
+        Decision::Branch(lit) => {
+            for &l in [lit, lit ^ 1].iter() {
+                let branch = lookahead.mark();
+                cube.push(cdcl::to_dimacs(l));
+                if lookahead.assign(l) {
+                    split(lookahead, config, cube, cubes);
+                }
+                cube.pop();
+                lookahead.undo(branch);
+            }
+        }
+    }
+    // Undo the literals forced by failed literal detection too
+    lookahead.undo(mark);
+}
+
+/// Write `cnf` and `cubes` in iCNF.
+pub fn write_icnf<W: Write>(out: &mut W, cnf: &CNF, cubes: &[Vec<i32>]) -> io::Result<()> {
+    writeln!(out, "p inccnf")?;
+    let mut ids: Vec<&i32> = cnf.clauses.keys().collect();
+    ids.sort();
+    for id in ids {
+        let mut clause: Vec<i32> = cnf.clauses[id]
+                                      .iter()
+                                      .map(|l| cdcl::to_dimacs(*l as usize))
+                                      .collect();
+        clause.sort_by_key(|l| (l.abs(), *l));
+        for lit in clause {
+            write!(out, "{} ", lit)?;
+        }
+        writeln!(out, "0")?;
+    }
+    for cube in cubes {
+        write!(out, "a ")?;
+        for lit in cube {
+            write!(out, "{} ", lit)?;
+        }
+        writeln!(out, "0")?;
+    }
+    Ok(())
+}
+
+/// Solve the cubes with `threads` incremental solvers, each taking the next unsolved cube until
+/// one finds a model. Returns the model, or `None` if every cube is unsatisfiable.
+pub fn conquer(cnf: &CNF,
+               cubes: &[Vec<i32>],
+               threads: usize,
+               config: &cdcl::Config,
+               verbose: bool)
+               -> Option<Assignment> {
+    let next = AtomicUsize::new(0);
+    let stop = Arc::new(AtomicBool::new(false));
+    let model: Mutex<Option<Assignment>> = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let stop = stop.clone();
+            let (next, model) = (&next, &model);
+            scope.spawn(move || {
+                let mut solver = Solver::new(cnf, config.clone());
+                solver.set_stop(stop.clone());
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= cubes.len() || stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let status = solver.solve_with(&cubes[i]);
+                    if verbose {
+                        println!("Cube {} of {}: {:?}", i + 1, cubes.len(), status);
+                    }
+                    if status == Status::Satisfiable {
+                        *model.lock().unwrap() = Some(solver.model().clone());
+                        stop.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    model.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use super::*;
+
+    #[test]
+    fn cubes_are_disjoint() {
+        let cnf = parse_file("tests/uf250-01.cnf");
+        let cubes = cube(&cnf, Config { depth: 4, cutoff: 0 });
+        assert!(!cubes.is_empty());
+        assert!(cubes.iter().all(|c| c.len() <= 4));
+        for (i, a) in cubes.iter().enumerate() {
+            for b in &cubes[i + 1..] {
+                assert!(a.iter().any(|l| b.contains(&-l)));
+            }
+        }
+    }
+
+    #[test]
+    fn cutoff_stops_splitting() {
+        let cnf = parse_file("tests/uf250-01.cnf");
+        assert_eq!(cube(&cnf, Config { depth: 4, cutoff: 250 }), vec![vec![]]);
+    }
+
+    #[test]
+    fn conquers_satisfiable() {
+        let cnf = parse_file("tests/uf250-01.cnf");
+        let cubes = cube(&cnf, Config { depth: 3, cutoff: 0 });
+        let model = conquer(&cnf, &cubes, 2, &cdcl::Config::default(), false).unwrap();
+        assert!(satisfies(&cnf, &model));
+    }
+
+    #[test]
+    fn conquers_pigeonhole() {
+        let cnf = parse_file("tests/phole/hole6.cnf");
+        let cubes = cube(&cnf, Config::default());
+        assert_eq!(conquer(&cnf, &cubes, 2, &cdcl::Config::default(), false), None);
+    }
+
+    #[test]
+    fn writes_icnf() {
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![1, -2]);
+        cnf.add_clause(vec![2, 3]);
+        let mut out = Vec::new();
+        write_icnf(&mut out, &cnf, &[vec![1], vec![-1, 3]]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "p inccnf\n1 -2 0\n2 3 0\na 1 0\na -1 3 0\n");
+    }
+}
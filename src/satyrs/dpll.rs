@@ -1,13 +1,41 @@
 //! DPLL Algorithm implementation with unit-clause propagation and
 //! pure literal assignment.
 
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use satyrs::cnf::{CNF, Assignment, PartialAssignment};
 use satyrs::heuristics::{jw, random};
+use satyrs::preprocess::Reconstruction;
+use satyrs::preprocess::blocked::eliminate_blocked;
+
+/// Extra controls over a DPLL search, for running it alongside other engines.
+#[derive(Default)]
+pub struct Options<'a> {
+    /// Preferred value of every variable. The heuristic still picks which variable to split on,
+    /// but its value here is tried first. Variables past the end keep the heuristic's polarity, so
+    /// this may start out empty. May be updated while the search runs.
+    pub phases: Option<&'a Mutex<Assignment>>,
+    /// Abandon the search once set. The result of a stopped search is `None`, whether or not the
+    /// formula is satisfiable.
+    pub stop: Option<&'a AtomicBool>,
+    /// Eliminate blocked clauses at every node this many splits deep, and at the root. Splits make
+    /// clauses shorter, and so more of them blocked.
+    pub inprocess: Option<usize>,
+}
 
 #[allow(non_snake_case)]
 pub fn DPLL(cnf: &CNF, verbose: bool) -> Option<(Assignment, PartialAssignment)> {
+    DPLL_with(cnf, verbose, &Options::default())
+}
+
+#[allow(non_snake_case)]
+pub fn DPLL_with(cnf: &CNF,
+                 verbose: bool,
+                 options: &Options)
+                 -> Option<(Assignment, PartialAssignment)> {
     let mut p_assn = PartialAssignment::new(cnf.nvar as usize);
-    match _dpll(cnf, &mut p_assn, verbose) {
+    match _dpll(cnf, &mut p_assn, verbose, options, 0) {
         Some(assn) => {
             Some((assn.assignment
                       .iter()
@@ -24,7 +52,15 @@ pub fn DPLL(cnf: &CNF, verbose: bool) -> Option<(Assignment, PartialAssignment)>
     }
 }
 
-fn _dpll(cnf: &CNF, p_assn: &mut PartialAssignment, verbose: bool) -> Option<PartialAssignment> {
+fn _dpll(cnf: &CNF,
+         p_assn: &mut PartialAssignment,
+         verbose: bool,
+         options: &Options,
+         depth: usize)
+         -> Option<PartialAssignment> {
+    if options.stop.is_some_and(|s| s.load(Ordering::Relaxed)) {
+        return None;
+    }
     if verbose {
         println!("====DPLL====\n");
         println!("{}\n{}", cnf, p_assn);
@@ -84,6 +120,47 @@ fn _dpll(cnf: &CNF, p_assn: &mut PartialAssignment, verbose: bool) -> Option<Par
         _cnf.propagate(*lit);
     }
 
+    // Blocked clause elimination. A model found below may falsify the removed clauses, so it gets
+    // repaired on the way back up.
+    let mut removed = Reconstruction::new();
+    if options.inprocess.is_some_and(|every| depth.is_multiple_of(every.max(1))) {
+        let stats = eliminate_blocked(&mut _cnf, &mut removed);
+        if verbose {
+            println!("Blocked clauses eliminated: {}", stats.clauses_removed);
+        }
+    }
+    _split(_cnf, p_assn, verbose, options, depth).map(|assn| repair(assn, &removed))
+}
+
+/// Set the witnesses of the clauses in `removed` that `p_assn` falsifies, with unassigned variables
+/// counting as true.
+fn repair(mut p_assn: PartialAssignment, removed: &Reconstruction) -> PartialAssignment {
+    if removed.is_empty() {
+        return p_assn;
+    }
+    let mut model: Assignment = p_assn.assignment.iter().map(|a| a.unwrap_or(true)).collect();
+    removed.extend(&mut model);
+    for (i, &value) in model.iter().enumerate() {
+        let var = (i + 1) as i32;
+        let lit = if value { var << 1 } else { var << 1 | 1 };
+        match p_assn.assignment[i] {
+            Some(old) if old == value => {}
+            Some(_) => {
+                p_assn.unassign_literal(lit ^ 1);
+                p_assn.assign_literal(lit);
+            }
+            None => p_assn.assign_literal(lit),
+        }
+    }
+    p_assn
+}
+
+fn _split(mut _cnf: CNF,
+          p_assn: &mut PartialAssignment,
+          verbose: bool,
+          options: &Options,
+          depth: usize)
+          -> Option<PartialAssignment> {
     // Choose literal L for split
     // Heuristics don't do random checks for empty occurrences.
     // If there are no occurrences,
@@ -109,7 +186,13 @@ fn _dpll(cnf: &CNF, p_assn: &mut PartialAssignment, verbose: bool) -> Option<Par
     let mut r_p_assn = p_assn.clone();
 
     // let lit = random(&_cnf);
-    let lit = jw(&_cnf);
+    let mut lit = jw(&_cnf);
+    if let Some(phases) = options.phases {
+        let var = lit >> 1;
+        if let Some(&value) = phases.lock().unwrap().get((var - 1) as usize) {
+            lit = if value { var << 1 } else { var << 1 | 1 };
+        }
+    }
 
     if verbose {
         if lit & 1 == 0 {
@@ -130,7 +213,7 @@ fn _dpll(cnf: &CNF, p_assn: &mut PartialAssignment, verbose: bool) -> Option<Par
     if verbose {
         println!("Trying left");
     }
-    let left = _dpll(&_cnf, p_assn, verbose);
+    let left = _dpll(&_cnf, p_assn, verbose, options, depth + 1);
     // If this branch works, return left.
     if left.is_some() {
         return left;
@@ -145,5 +228,5 @@ fn _dpll(cnf: &CNF, p_assn: &mut PartialAssignment, verbose: bool) -> Option<Par
     if verbose {
         println!("Trying right");
     }
-    _dpll(&r_cnf, &mut r_p_assn, verbose)
+    _dpll(&r_cnf, &mut r_p_assn, verbose, options, depth + 1)
 }
@@ -0,0 +1,199 @@
+//! At-most-one constraints. These dominate encodings such as sudoku (every cell holds at most one
+//! digit), where the quadratic pairwise encoding quickly swamps everything else.
+
+use satyrs::cnf::CNF;
+
+/// Groups of at most this many literals are always encoded pairwise.
+const PAIRWISE_CUTOFF: usize = 4;
+
+/// Size of the groups managed by a single commander variable.
+const COMMANDER_GROUP: usize = 3;
+
+/// Strategy for encoding "at most one of these literals is true".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmoEncoding {
+    /// A binary clause for every pair: O(n^2) clauses, no auxiliary variables.
+    Pairwise,
+    /// Sequential counter with a single register, also known as the ladder: 3n clauses.
+    Ladder,
+    /// Klieber and Kwon's commander encoding, recursing on the commanders of small groups.
+    Commander,
+    /// Chen's product encoding, placing the literals on a sqrt(n) by sqrt(n) grid.
+    Product,
+    /// Nguyen and Mai's bimander encoding: pairs of literals share a binary commander.
+    Bimander,
+}
+
+/// At most one of `lits` may be true.
+pub fn at_most_one(cnf: &mut CNF, lits: &[i32], encoding: AmoEncoding) {
+    if lits.len() <= 1 {
+        return;
+    }
+    match encoding {
+        AmoEncoding::Pairwise => pairwise(cnf, lits),
+        AmoEncoding::Ladder => ladder(cnf, lits),
+        AmoEncoding::Commander => commander(cnf, lits),
+        AmoEncoding::Product => product(cnf, lits),
+        AmoEncoding::Bimander => bimander(cnf, lits),
+    }
+}
+
+/// Exactly one of `lits` must be true.
+pub fn exactly_one(cnf: &mut CNF, lits: &[i32], encoding: AmoEncoding) {
+    if lits.is_empty() {
+        super::contradiction(cnf);
+        return;
+    }
+    cnf.add_clause(lits.to_vec());
+    at_most_one(cnf, lits, encoding);
+}
+
+fn pairwise(cnf: &mut CNF, lits: &[i32]) {
+    for (i, a) in lits.iter().enumerate() {
+        for b in &lits[i + 1..] {
+            cnf.add_clause(vec![-*a, -*b]);
+        }
+    }
+}
+
+/// Register `s[i]` is true if any of the first `i + 1` literals is; a true literal may not follow
+/// a set register.
+fn ladder(cnf: &mut CNF, lits: &[i32]) {
+    let n = lits.len();
+    let s: Vec<i32> = (0..n - 1).map(|_| cnf.new_var()).collect();
+    cnf.add_clause(vec![-lits[0], s[0]]);
+    for i in 1..n - 1 {
+        cnf.add_clause(vec![-lits[i], s[i]]);
+        cnf.add_clause(vec![-s[i - 1], s[i]]);
+        cnf.add_clause(vec![-lits[i], -s[i - 1]]);
+    }
+    cnf.add_clause(vec![-lits[n - 1], -s[n - 2]]);
+}
+
+/// Each group of literals gets a commander that is implied by any of them; at most one literal per
+/// group and at most one commander overall.
+fn commander(cnf: &mut CNF, lits: &[i32]) {
+    if lits.len() <= PAIRWISE_CUTOFF {
+        return pairwise(cnf, lits);
+    }
+    let mut commanders = Vec::new();
+    for group in lits.chunks(COMMANDER_GROUP) {
+        let c = cnf.new_var();
+        pairwise(cnf, group);
+        for lit in group {
+            cnf.add_clause(vec![-*lit, c]);
+        }
+        commanders.push(c);
+    }
+    commander(cnf, &commanders);
+}
+
+/// A true literal at grid position `(i, j)` sets row `i` and column `j`; at most one row and at
+/// most one column may be set, recursively.
+fn product(cnf: &mut CNF, lits: &[i32]) {
+    if lits.len() <= PAIRWISE_CUTOFF {
+        return pairwise(cnf, lits);
+    }
+    let n = lits.len();
+    let p = (n as f64).sqrt().ceil() as usize;
+    let q = n.div_ceil(p);
+    let rows: Vec<i32> = (0..p).map(|_| cnf.new_var()).collect();
+    let cols: Vec<i32> = (0..q).map(|_| cnf.new_var()).collect();
+    for (idx, lit) in lits.iter().enumerate() {
+        cnf.add_clause(vec![-*lit, rows[idx / q]]);
+        cnf.add_clause(vec![-*lit, cols[idx % q]]);
+    }
+    product(cnf, &rows);
+    product(cnf, &cols);
+}
+
+/// Split the literals into pairs, and give every pair a distinct binary code over shared bit
+/// variables. A true literal forces its pair's code, so two literals from different pairs clash.
+fn bimander(cnf: &mut CNF, lits: &[i32]) {
+    let groups: Vec<&[i32]> = lits.chunks(2).collect();
+    let mut nbits = 0;
+    while 1 << nbits < groups.len() {
+        nbits += 1;
+    }
+    let bits: Vec<i32> = (0..nbits).map(|_| cnf.new_var()).collect();
+    for (i, group) in groups.iter().enumerate() {
+        pairwise(cnf, group);
+        for lit in group.iter() {
+            for (j, bit) in bits.iter().enumerate() {
+                let phase = if i >> j & 1 == 1 { *bit } else { -*bit };
+                cnf.add_clause(vec![-*lit, phase]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::CNF;
+    use satyrs::encode::tests::{assignments, satisfiable_with};
+
+    use super::*;
+
+    const ENCODINGS: [AmoEncoding; 4] = [AmoEncoding::Ladder,
+                                         AmoEncoding::Commander,
+                                         AmoEncoding::Product,
+                                         AmoEncoding::Bimander];
+
+    fn encoded(n: i32, encoding: AmoEncoding, exact: bool) -> CNF {
+        let lits: Vec<i32> = (1..n + 1).collect();
+        let mut cnf = CNF::new(n, 0);
+        if exact {
+            exactly_one(&mut cnf, &lits, encoding);
+        } else {
+            at_most_one(&mut cnf, &lits, encoding);
+        }
+        cnf
+    }
+
+    #[test]
+    fn pairwise_works() {
+        let cnf = encoded(4, AmoEncoding::Pairwise, false);
+        for assn in assignments(4) {
+            let count = assn.iter().filter(|l| **l > 0).count();
+            assert_eq!(satisfiable_with(&cnf, &assn), count <= 1);
+        }
+    }
+
+    #[test]
+    fn encodings_match_pairwise() {
+        for n in 2..10 {
+            let reference = encoded(n, AmoEncoding::Pairwise, false);
+            for encoding in ENCODINGS.iter() {
+                let cnf = encoded(n, *encoding, false);
+                for assn in assignments(n) {
+                    assert_eq!(satisfiable_with(&cnf, &assn),
+                               satisfiable_with(&reference, &assn),
+                               "{:?} on {:?}",
+                               encoding,
+                               assn);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exactly_one_matches_pairwise() {
+        for n in 1..8 {
+            let reference = encoded(n, AmoEncoding::Pairwise, true);
+            for encoding in ENCODINGS.iter() {
+                let cnf = encoded(n, *encoding, true);
+                for assn in assignments(n) {
+                    assert_eq!(satisfiable_with(&cnf, &assn),
+                               satisfiable_with(&reference, &assn));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bimander_is_smaller_than_pairwise() {
+        let pairwise = encoded(20, AmoEncoding::Pairwise, false);
+        let bimander = encoded(20, AmoEncoding::Bimander, false);
+        assert!(bimander.clauses.len() < pairwise.clauses.len());
+    }
+}
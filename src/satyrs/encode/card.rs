@@ -0,0 +1,229 @@
+//! Cardinality constraints: at most, at least and exactly `k` of a list of literals.
+//!
+//! All three encodings only constrain the counter "upwards" (if `j` inputs are true then the
+//! `j`th output is true), which is all that is needed once the `k + 1`th output is forced false.
+//! `at_least` is `at_most` over the negated literals.
+
+use satyrs::cnf::CNF;
+use super::contradiction;
+
+/// Which circuit to use for counting the true literals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardEncoding {
+    /// Sinz's sequential counter: O(nk) clauses and auxiliary variables.
+    SequentialCounter,
+    /// Bailleux and Boufkhad's totalizer: a tree of unary adders, O(n log n) variables.
+    Totalizer,
+    /// Sorting network built from Batcher's odd-even merge: O(n log^2 n) clauses.
+    CardinalityNetwork,
+}
+
+/// At most `k` of `lits` may be true.
+pub fn at_most(cnf: &mut CNF, lits: &[i32], k: usize, encoding: CardEncoding) {
+    if k >= lits.len() {
+        return;
+    }
+    if k == 0 {
+        for lit in lits {
+            cnf.add_clause(vec![-*lit]);
+        }
+        return;
+    }
+    match encoding {
+        CardEncoding::SequentialCounter => sequential_counter(cnf, lits, k),
+        CardEncoding::Totalizer => {
+            let outputs = totalizer(cnf, lits);
+            cnf.add_clause(vec![-outputs[k]]);
+        }
+        CardEncoding::CardinalityNetwork => {
+            let outputs = sorting_network(cnf, lits);
+            cnf.add_clause(vec![-outputs[k]]);
+        }
+    }
+}
+
+/// At least `k` of `lits` must be true.
+pub fn at_least(cnf: &mut CNF, lits: &[i32], k: usize, encoding: CardEncoding) {
+    if k > lits.len() {
+        contradiction(cnf);
+        return;
+    }
+    let negated: Vec<i32> = lits.iter().map(|l| -l).collect();
+    at_most(cnf, &negated, lits.len() - k, encoding);
+}
+
+/// Exactly `k` of `lits` must be true.
+pub fn exactly(cnf: &mut CNF, lits: &[i32], k: usize, encoding: CardEncoding) {
+    at_most(cnf, lits, k, encoding);
+    at_least(cnf, lits, k, encoding);
+}
+
+/// Sequential counter for `sum(lits) <= k`, with `0 < k < lits.len()`. Register `s[i][j]` is true
+/// whenever at least `j + 1` of the first `i + 1` literals are true.
+fn sequential_counter(cnf: &mut CNF, lits: &[i32], k: usize) {
+    let n = lits.len();
+    let s: Vec<Vec<i32>> = (0..n - 1).map(|_| (0..k).map(|_| cnf.new_var()).collect()).collect();
+
+    cnf.add_clause(vec![-lits[0], s[0][0]]);
+    for reg in &s[0][1..] {
+        cnf.add_clause(vec![-*reg]);
+    }
+    for i in 1..n - 1 {
+        cnf.add_clause(vec![-lits[i], s[i][0]]);
+        cnf.add_clause(vec![-s[i - 1][0], s[i][0]]);
+        for j in 1..k {
+            cnf.add_clause(vec![-lits[i], -s[i - 1][j - 1], s[i][j]]);
+            cnf.add_clause(vec![-s[i - 1][j], s[i][j]]);
+        }
+        cnf.add_clause(vec![-lits[i], -s[i - 1][k - 1]]);
+    }
+    cnf.add_clause(vec![-lits[n - 1], -s[n - 2][k - 1]]);
+}
+
+/// Build a totalizer over `lits` and return its unary outputs: the `j`th output (0-indexed) is
+/// forced true whenever more than `j` of `lits` are true. The outputs are exposed so that callers
+/// can tighten a bound incrementally by asserting further outputs false.
+pub fn totalizer(cnf: &mut CNF, lits: &[i32]) -> Vec<i32> {
+    if lits.len() <= 1 {
+        return lits.to_vec();
+    }
+    let (left, right) = lits.split_at(lits.len() / 2);
+    let a = totalizer(cnf, left);
+    let b = totalizer(cnf, right);
+    let outputs: Vec<i32> = (0..lits.len()).map(|_| cnf.new_var()).collect();
+    // a[i - 1] (i true on the left) and b[j - 1] (j true on the right) give i + j true overall
+    for i in 0..a.len() + 1 {
+        for j in 0..b.len() + 1 {
+            if i + j == 0 {
+                continue;
+            }
+            let mut clause = vec![outputs[i + j - 1]];
+            if i > 0 {
+                clause.push(-a[i - 1]);
+            }
+            if j > 0 {
+                clause.push(-b[j - 1]);
+            }
+            cnf.add_clause(clause);
+        }
+    }
+    outputs
+}
+
+/// Sort `lits` into descending unary order with an odd-even merge sorting network. As with
+/// `totalizer`, the `j`th output is forced true whenever more than `j` of `lits` are true.
+pub fn sorting_network(cnf: &mut CNF, lits: &[i32]) -> Vec<i32> {
+    if lits.len() <= 1 {
+        return lits.to_vec();
+    }
+    let (left, right) = lits.split_at(lits.len() / 2);
+    let a = sorting_network(cnf, left);
+    let b = sorting_network(cnf, right);
+    merge(cnf, &a, &b)
+}
+
+/// Batcher's odd-even merge of two sorted sequences of arbitrary length.
+fn merge(cnf: &mut CNF, a: &[i32], b: &[i32]) -> Vec<i32> {
+    if a.is_empty() {
+        return b.to_vec();
+    }
+    if b.is_empty() {
+        return a.to_vec();
+    }
+    if a.len() == 1 && b.len() == 1 {
+        let (hi, lo) = comparator(cnf, a[0], b[0]);
+        return vec![hi, lo];
+    }
+    let evens = |xs: &[i32]| -> Vec<i32> { xs.iter().cloned().step_by(2).collect() };
+    let odds = |xs: &[i32]| -> Vec<i32> { xs.iter().cloned().skip(1).step_by(2).collect() };
+    let v = merge(cnf, &evens(a), &evens(b));
+    let w = merge(cnf, &odds(a), &odds(b));
+
+    let mut outputs = vec![v[0]];
+    let mut i = 0;
+    while i < w.len() && i + 1 < v.len() {
+        let (hi, lo) = comparator(cnf, w[i], v[i + 1]);
+        outputs.push(hi);
+        outputs.push(lo);
+        i += 1;
+    }
+    // At most one element is left over, and it belongs at the end.
+    outputs.extend_from_slice(&w[i..]);
+    outputs.extend_from_slice(&v[i + 1..]);
+    outputs
+}
+
+/// A two-input comparator, returning `(a or b, a and b)`.
+fn comparator(cnf: &mut CNF, a: i32, b: i32) -> (i32, i32) {
+    let hi = cnf.new_var();
+    let lo = cnf.new_var();
+    cnf.add_clause(vec![-a, hi]);
+    cnf.add_clause(vec![-b, hi]);
+    cnf.add_clause(vec![-a, -b, lo]);
+    (hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::CNF;
+    use satyrs::encode::tests::{assignments, satisfiable_with};
+
+    use super::*;
+
+    const ENCODINGS: [CardEncoding; 3] = [CardEncoding::SequentialCounter,
+                                          CardEncoding::Totalizer,
+                                          CardEncoding::CardinalityNetwork];
+
+    /// Check `encode` against `holds` on every assignment to `n` variables.
+    fn check<F, P>(n: i32, encode: F, holds: P)
+        where F: Fn(&mut CNF, &[i32]),
+              P: Fn(usize) -> bool
+    {
+        let lits: Vec<i32> = (1..n + 1).collect();
+        let mut cnf = CNF::new(n, 0);
+        encode(&mut cnf, &lits);
+        for assn in assignments(n) {
+            let count = assn.iter().filter(|l| **l > 0).count();
+            assert_eq!(satisfiable_with(&cnf, &assn), holds(count), "{:?}", assn);
+        }
+    }
+
+    #[test]
+    fn at_most_works() {
+        for encoding in ENCODINGS.iter() {
+            for n in 1..6 {
+                for k in 0..n as usize + 1 {
+                    check(n, |cnf, lits| at_most(cnf, lits, k, *encoding), |c| c <= k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn at_least_works() {
+        for encoding in ENCODINGS.iter() {
+            for n in 1..5 {
+                for k in 0..n as usize + 2 {
+                    check(n, |cnf, lits| at_least(cnf, lits, k, *encoding), |c| c >= k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exactly_works() {
+        for encoding in ENCODINGS.iter() {
+            for k in 0..5 {
+                check(4, |cnf, lits| exactly(cnf, lits, k, *encoding), |c| c == k);
+            }
+        }
+    }
+
+    #[test]
+    fn handles_negative_literals() {
+        let mut cnf = CNF::new(3, 0);
+        at_most(&mut cnf, &[1, -2, 3], 1, CardEncoding::Totalizer);
+        assert!(satisfiable_with(&cnf, &[1, 2, -3]));
+        assert!(!satisfiable_with(&cnf, &[1, -2, -3]));
+    }
+}
@@ -0,0 +1,477 @@
+//! Bounded integer variables, bit-blasted into a `CNF`.
+//!
+//! An `IntVar` ranges over `lo..hi + 1` and is represented in one of two ways:
+//!
+//! * the order encoding keeps one literal per value `v` in `lo + 1..hi + 1`, true iff `x >= v`.
+//!   Comparisons and addition propagate well, but the size is linear in the domain;
+//! * the log encoding keeps the binary digits of `x - lo`, least significant first. It is
+//!   logarithmic in the domain, and arithmetic goes through ripple-carry adders.
+//!
+//! Variables built from others (sums, scaled copies) reuse the same encoding. The two encodings
+//! cannot be mixed in one operation: comparing or adding variables of different encodings is an
+//! error, and adds nothing to the CNF.
+
+use std::cmp;
+
+use satyrs::cnf::{Assignment, CNF};
+use super::contradiction;
+use super::pb::{full_adder, half_adder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntEncoding {
+    Order,
+    Log,
+}
+
+/// A single bit of an encoding: either a fixed value or a DIMACS literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bit {
+    Const(bool),
+    Lit(i32),
+}
+
+impl Bit {
+    fn negate(self) -> Bit {
+        match self {
+            Bit::Const(b) => Bit::Const(!b),
+            Bit::Lit(l) => Bit::Lit(-l),
+        }
+    }
+
+    fn value(self, assn: &Assignment) -> bool {
+        match self {
+            Bit::Const(b) => b,
+            Bit::Lit(l) => assn[(l.abs() - 1) as usize] == (l > 0),
+        }
+    }
+}
+
+/// Add the clause over `bits`, dropping false constants. A true constant satisfies the clause
+/// outright, and a clause with nothing left in it makes the formula unsatisfiable.
+fn add_bits_clause(cnf: &mut CNF, bits: &[Bit]) {
+    let mut clause = Vec::new();
+    for bit in bits {
+        match *bit {
+            Bit::Const(true) => return,
+            Bit::Const(false) => {}
+            Bit::Lit(l) => clause.push(l),
+        }
+    }
+    if clause.is_empty() {
+        contradiction(cnf);
+    } else {
+        cnf.add_clause(clause);
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Repr {
+    /// `ge[i]` is `x >= lo + i + 1`.
+    Order(Vec<Bit>),
+    /// Binary digits of `x - lo`, least significant first.
+    Log(Vec<Bit>),
+}
+
+#[derive(Debug, Clone)]
+pub struct IntVar {
+    pub lo: i64,
+    pub hi: i64,
+    repr: Repr,
+}
+
+impl IntVar {
+    /// A fresh integer variable ranging over `lo..hi + 1`.
+    pub fn new(cnf: &mut CNF, lo: i64, hi: i64, encoding: IntEncoding) -> IntVar {
+        assert!(lo <= hi, "empty domain");
+        match encoding {
+            IntEncoding::Order => {
+                let ge: Vec<Bit> = (lo..hi).map(|_| Bit::Lit(cnf.new_var())).collect();
+                // x >= v + 1 implies x >= v
+                for pair in ge.windows(2) {
+                    add_bits_clause(cnf, &[pair[1].negate(), pair[0]]);
+                }
+                IntVar { lo, hi, repr: Repr::Order(ge) }
+            }
+            IntEncoding::Log => {
+                let bits: Vec<Bit> = (0..bits_needed(hi - lo))
+                                         .map(|_| Bit::Lit(cnf.new_var()))
+                                         .collect();
+                let x = IntVar { lo, hi, repr: Repr::Log(bits) };
+                // Rule out the binary values past the top of the domain.
+                let top = IntVar::constant(hi, IntEncoding::Log);
+                x.le_in(cnf, &top, IntEncoding::Log);
+                x
+            }
+        }
+    }
+
+    /// An integer fixed to `value`, for comparing against or adding to other variables.
+    pub fn constant(value: i64, encoding: IntEncoding) -> IntVar {
+        let repr = match encoding {
+            IntEncoding::Order => Repr::Order(Vec::new()),
+            IntEncoding::Log => Repr::Log(Vec::new()),
+        };
+        IntVar { lo: value, hi: value, repr }
+    }
+
+    pub fn encoding(&self) -> IntEncoding {
+        match self.repr {
+            Repr::Order(_) => IntEncoding::Order,
+            Repr::Log(_) => IntEncoding::Log,
+        }
+    }
+
+    /// Read the value of this variable off a satisfying assignment.
+    pub fn value(&self, assn: &Assignment) -> i64 {
+        match self.repr {
+            Repr::Order(ref ge) => self.lo + ge.iter().take_while(|b| b.value(assn)).count() as i64,
+            Repr::Log(ref bits) => {
+                self.lo +
+                bits.iter()
+                    .enumerate()
+                    .filter(|&(_, b)| b.value(assn))
+                    .map(|(i, _)| 1 << i)
+                    .sum::<i64>()
+            }
+        }
+    }
+
+    /// Order encoding only: the bit for `x >= v`.
+    fn at_least(&self, v: i64) -> Bit {
+        match self.repr {
+            Repr::Order(ref ge) => {
+                if v <= self.lo {
+                    Bit::Const(true)
+                } else if v > self.hi {
+                    Bit::Const(false)
+                } else {
+                    ge[(v - self.lo - 1) as usize]
+                }
+            }
+            Repr::Log(_) => unreachable!(),
+        }
+    }
+
+    /// Log encoding only: binary digits of `x - offset`, with `offset <= lo`.
+    fn bits_from(&self, cnf: &mut CNF, offset: i64) -> Vec<Bit> {
+        match self.repr {
+            Repr::Log(ref bits) => {
+                let shift = constant_bits(self.lo - offset);
+                ripple_add(cnf, bits, &shift)
+            }
+            Repr::Order(_) => unreachable!(),
+        }
+    }
+
+    /// The encoding of both `self` and `other`, if they have the same one.
+    fn common_encoding(&self, other: &IntVar) -> Result<IntEncoding, &'static str> {
+        if self.encoding() == other.encoding() {
+            Ok(self.encoding())
+        } else {
+            Err("cannot mix integer encodings")
+        }
+    }
+
+    /// Constrain `self <= other`.
+    pub fn le(&self, cnf: &mut CNF, other: &IntVar) -> Result<(), &'static str> {
+        let encoding = self.common_encoding(other)?;
+        self.le_in(cnf, other, encoding);
+        Ok(())
+    }
+
+    /// `le` for two variables known to both have `encoding`.
+    fn le_in(&self, cnf: &mut CNF, other: &IntVar, encoding: IntEncoding) {
+        match encoding {
+            IntEncoding::Order => {
+                // x >= v implies y >= v, starting from x >= lo which always holds
+                for v in self.lo..self.hi + 1 {
+                    add_bits_clause(cnf, &[self.at_least(v).negate(), other.at_least(v)]);
+                }
+            }
+            IntEncoding::Log => {
+                let offset = cmp::min(self.lo, other.lo);
+                let a = self.bits_from(cnf, offset);
+                let b = other.bits_from(cnf, offset);
+                lex_le(cnf, &a, &b);
+            }
+        }
+    }
+
+    /// Constrain `self >= other`.
+    pub fn ge(&self, cnf: &mut CNF, other: &IntVar) -> Result<(), &'static str> {
+        other.le(cnf, self)
+    }
+
+    /// Constrain `self == other`.
+    pub fn eq(&self, cnf: &mut CNF, other: &IntVar) -> Result<(), &'static str> {
+        let encoding = self.common_encoding(other)?;
+        self.le_in(cnf, other, encoding);
+        other.le_in(cnf, self, encoding);
+        Ok(())
+    }
+
+    pub fn le_const(&self, cnf: &mut CNF, c: i64) {
+        self.le_in(cnf, &IntVar::constant(c, self.encoding()), self.encoding());
+    }
+
+    pub fn ge_const(&self, cnf: &mut CNF, c: i64) {
+        IntVar::constant(c, self.encoding()).le_in(cnf, self, self.encoding());
+    }
+
+    pub fn eq_const(&self, cnf: &mut CNF, c: i64) {
+        self.le_const(cnf, c);
+        self.ge_const(cnf, c);
+    }
+
+    /// A new variable equal to `self + other`.
+    pub fn add(&self, cnf: &mut CNF, other: &IntVar) -> Result<IntVar, &'static str> {
+        let (lo, hi) = (self.lo + other.lo, self.hi + other.hi);
+        let sum = match self.common_encoding(other)? {
+            IntEncoding::Order => {
+                let z = IntVar::new(cnf, lo, hi, IntEncoding::Order);
+                for a in self.lo..self.hi + 1 {
+                    for b in other.lo..other.hi + 1 {
+                        // x >= a and y >= b imply z >= a + b
+                        add_bits_clause(cnf,
+                                        &[self.at_least(a).negate(),
+                                          other.at_least(b).negate(),
+                                          z.at_least(a + b)]);
+                        // x <= a and y <= b imply z <= a + b
+                        add_bits_clause(cnf,
+                                        &[self.at_least(a + 1),
+                                          other.at_least(b + 1),
+                                          z.at_least(a + b + 1).negate()]);
+                    }
+                }
+                z
+            }
+            IntEncoding::Log => {
+                let a = self.bits_from(cnf, self.lo);
+                let b = other.bits_from(cnf, other.lo);
+                IntVar { lo, hi, repr: Repr::Log(ripple_add(cnf, &a, &b)) }
+            }
+        };
+        Ok(sum)
+    }
+
+    /// A new variable equal to `c * self`, for a constant `c >= 0`.
+    pub fn scale(&self, cnf: &mut CNF, c: i64) -> IntVar {
+        assert!(c >= 0, "can only scale by non-negative constants");
+        if c == 0 {
+            return IntVar::constant(0, self.encoding());
+        }
+        let (lo, hi) = (c * self.lo, c * self.hi);
+        match self.repr {
+            // c * x >= v iff x >= ceil(v / c), so the literals of x can be reused directly.
+            Repr::Order(_) => {
+                let ge = (lo + 1..hi + 1).map(|v| self.at_least(div_ceil(v, c))).collect();
+                IntVar { lo, hi, repr: Repr::Order(ge) }
+            }
+            // Shift and add: c * (x - lo) is the sum of (x - lo) << k over the set bits k of c.
+            Repr::Log(ref bits) => {
+                let mut sum: Vec<Bit> = Vec::new();
+                for k in 0..64 - c.leading_zeros() as usize {
+                    if c >> k & 1 == 1 {
+                        let mut shifted = vec![Bit::Const(false); k];
+                        shifted.extend_from_slice(bits);
+                        sum = ripple_add(cnf, &sum, &shifted);
+                    }
+                }
+                IntVar { lo, hi, repr: Repr::Log(sum) }
+            }
+        }
+    }
+}
+
+fn div_ceil(a: i64, b: i64) -> i64 {
+    let q = a / b;
+    if a % b != 0 && (a < 0) == (b < 0) { q + 1 } else { q }
+}
+
+/// Number of binary digits needed to write `n`.
+fn bits_needed(n: i64) -> usize {
+    64 - n.leading_zeros() as usize
+}
+
+/// The binary digits of `n >= 0` as constants.
+fn constant_bits(n: i64) -> Vec<Bit> {
+    (0..bits_needed(n)).map(|i| Bit::Const(n >> i & 1 == 1)).collect()
+}
+
+/// Add two binary numbers with a ripple-carry adder.
+fn ripple_add(cnf: &mut CNF, a: &[Bit], b: &[Bit]) -> Vec<Bit> {
+    let mut sum = Vec::new();
+    let mut carry = Bit::Const(false);
+    for i in 0..cmp::max(a.len(), b.len()) {
+        let x = *a.get(i).unwrap_or(&Bit::Const(false));
+        let y = *b.get(i).unwrap_or(&Bit::Const(false));
+        let (s, c) = add_bits(cnf, x, y, carry);
+        sum.push(s);
+        carry = c;
+    }
+    if carry != Bit::Const(false) {
+        sum.push(carry);
+    }
+    sum
+}
+
+/// One position of the adder, returning `(sum, carry)` and simplifying constant inputs.
+fn add_bits(cnf: &mut CNF, x: Bit, y: Bit, z: Bit) -> (Bit, Bit) {
+    let mut ones = 0;
+    let mut lits = Vec::new();
+    for bit in &[x, y, z] {
+        match *bit {
+            Bit::Const(true) => ones += 1,
+            Bit::Const(false) => {}
+            Bit::Lit(l) => lits.push(l),
+        }
+    }
+    match (lits.len(), ones) {
+        (0, n) => (Bit::Const(n & 1 == 1), Bit::Const(n >= 2)),
+        (1, 0) => (Bit::Lit(lits[0]), Bit::Const(false)),
+        (1, 1) => (Bit::Lit(-lits[0]), Bit::Lit(lits[0])),
+        (1, _) => (Bit::Lit(lits[0]), Bit::Const(true)),
+        (2, 0) => {
+            let (s, c) = half_adder(cnf, lits[0], lits[1]);
+            (Bit::Lit(s), Bit::Lit(c))
+        }
+        (2, _) => {
+            // a + b + 1: the sum is xnor, the carry is or = xor or and
+            let (s, c) = half_adder(cnf, lits[0], lits[1]);
+            let or = cnf.new_var();
+            cnf.add_clause(vec![-s, or]);
+            cnf.add_clause(vec![-c, or]);
+            cnf.add_clause(vec![s, c, -or]);
+            (Bit::Lit(-s), Bit::Lit(or))
+        }
+        _ => {
+            let (s, c) = full_adder(cnf, lits[0], lits[1], lits[2]);
+            (Bit::Lit(s), Bit::Lit(c))
+        }
+    }
+}
+
+/// Constrain the binary number `a` to be at most `b`. Reading from the most significant bit,
+/// `le[i]` implies that the digits up to `i` of `a` are at most those of `b`.
+fn lex_le(cnf: &mut CNF, a: &[Bit], b: &[Bit]) {
+    let n = cmp::max(a.len(), b.len());
+    // le for the digits below the lowest: trivially true
+    let mut below = Bit::Const(true);
+    for i in 0..n {
+        let x = *a.get(i).unwrap_or(&Bit::Const(false));
+        let y = *b.get(i).unwrap_or(&Bit::Const(false));
+        let le = if i + 1 == n { Bit::Const(true) } else { Bit::Lit(cnf.new_var()) };
+        let not_le = le.negate();
+        // Never a = 1, b = 0 here; when they are equal the lower digits decide.
+        add_bits_clause(cnf, &[not_le, x.negate(), y]);
+        add_bits_clause(cnf, &[not_le, y, below]);
+        add_bits_clause(cnf, &[not_le, x.negate(), below]);
+        below = le;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::CNF;
+    use satyrs::dpll::DPLL;
+    use satyrs::encode::tests::{assignments, satisfiable_with};
+
+    use super::*;
+
+    const ENCODINGS: [IntEncoding; 2] = [IntEncoding::Order, IntEncoding::Log];
+
+    /// Solve `cnf` with `x = a` and `y = b`, returning the model if there is one.
+    fn solve_at(cnf: &CNF, x: &IntVar, a: i64, y: &IntVar, b: i64) -> Option<Vec<bool>> {
+        let mut cnf = cnf.clone();
+        x.eq_const(&mut cnf, a);
+        y.eq_const(&mut cnf, b);
+        DPLL(&cnf, false).map(|(assn, _)| assn)
+    }
+
+    #[test]
+    fn addition() {
+        for encoding in ENCODINGS.iter() {
+            let mut cnf = CNF::new(0, 0);
+            let x = IntVar::new(&mut cnf, -2, 3, *encoding);
+            let y = IntVar::new(&mut cnf, 1, 5, *encoding);
+            let z = x.add(&mut cnf, &y).unwrap();
+            for a in -2..4 {
+                for b in 1..6 {
+                    let model = solve_at(&cnf, &x, a, &y, b).unwrap();
+                    assert_eq!(x.value(&model), a);
+                    assert_eq!(z.value(&model), a + b, "{:?}", encoding);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn comparison() {
+        for encoding in ENCODINGS.iter() {
+            let mut cnf = CNF::new(0, 0);
+            let x = IntVar::new(&mut cnf, 0, 6, *encoding);
+            let y = IntVar::new(&mut cnf, 2, 4, *encoding);
+            x.le(&mut cnf, &y).unwrap();
+            for a in 0..7 {
+                for b in 2..5 {
+                    assert_eq!(solve_at(&cnf, &x, a, &y, b).is_some(), a <= b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scaling() {
+        for encoding in ENCODINGS.iter() {
+            let mut cnf = CNF::new(0, 0);
+            let x = IntVar::new(&mut cnf, 1, 4, *encoding);
+            let y = IntVar::new(&mut cnf, 0, 20, *encoding);
+            let three_x = x.scale(&mut cnf, 3);
+            three_x.eq(&mut cnf, &y).unwrap();
+            for a in 1..5 {
+                let model = solve_at(&cnf, &x, a, &y, 3 * a).unwrap();
+                assert_eq!(three_x.value(&model), 3 * a);
+                assert!(solve_at(&cnf, &x, a, &y, 3 * a + 1).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn log_domain_is_bounded() {
+        let mut cnf = CNF::new(0, 0);
+        let x = IntVar::new(&mut cnf, 10, 15, IntEncoding::Log);
+        // Three bits, of which only 0..6 are in the domain
+        for assn in assignments(3) {
+            let offset: i64 = assn.iter()
+                                  .enumerate()
+                                  .filter(|&(_, l)| *l > 0)
+                                  .map(|(i, _)| 1 << i)
+                                  .sum();
+            assert_eq!(satisfiable_with(&cnf, &assn), offset <= 5);
+        }
+        let model = DPLL(&cnf, false).unwrap().0;
+        assert!(x.value(&model) >= 10 && x.value(&model) <= 15);
+    }
+
+    #[test]
+    fn mixed_encodings() {
+        let mut cnf = CNF::new(0, 0);
+        let x = IntVar::new(&mut cnf, 0, 3, IntEncoding::Order);
+        let y = IntVar::new(&mut cnf, 0, 3, IntEncoding::Log);
+        let before = (cnf.nvar, cnf.clauses.len());
+        assert!(x.le(&mut cnf, &y).is_err());
+        assert!(y.eq(&mut cnf, &x).is_err());
+        assert!(x.add(&mut cnf, &y).is_err());
+        assert_eq!((cnf.nvar, cnf.clauses.len()), before);
+    }
+
+    #[test]
+    fn constants_out_of_range() {
+        for encoding in ENCODINGS.iter() {
+            let mut cnf = CNF::new(0, 0);
+            let x = IntVar::new(&mut cnf, 0, 3, *encoding);
+            x.ge_const(&mut cnf, 4);
+            assert!(DPLL(&cnf, false).is_none());
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Encoders that translate higher-level constraints into clauses of a `CNF`.
+//!
+//! Unlike the rest of satyrs, the encoders speak DIMACS literals (`3` and `-3`) rather than the
+//! internal `var << 1 | sign` representation, since that is what `CNF::add_clause` takes.
+//! Auxiliary variables are allocated on the fly with `CNF::new_var`.
+
+use satyrs::cnf::CNF;
+
+pub mod card;
+pub mod amo;
+pub mod pb;
+pub mod int;
+
+pub use self::card::{CardEncoding, at_least, at_most, exactly};
+pub use self::amo::{AmoEncoding, at_most_one, exactly_one};
+pub use self::pb::{PbEncoding, Relation, pseudo_boolean};
+pub use self::int::{IntEncoding, IntVar};
+
+/// Make `cnf` unsatisfiable. `CNF` has no way of storing an empty clause, so we assert a fresh
+/// variable both ways instead.
+fn contradiction(cnf: &mut CNF) {
+    let v = cnf.new_var();
+    cnf.add_clause(vec![v]);
+    cnf.add_clause(vec![-v]);
+}
+
+#[cfg(test)]
+pub mod tests {
+    use satyrs::cnf::CNF;
+    use satyrs::dpll::DPLL;
+
+    /// Is `cnf` satisfiable once every literal in `assumptions` is forced true?
+    pub fn satisfiable_with(cnf: &CNF, assumptions: &[i32]) -> bool {
+        let mut cnf = cnf.clone();
+        for lit in assumptions {
+            cnf.add_clause(vec![*lit]);
+        }
+        DPLL(&cnf, false).is_some()
+    }
+
+    /// Every assignment to variables `1..n + 1`, as lists of DIMACS literals.
+    pub fn assignments(n: i32) -> Vec<Vec<i32>> {
+        (0..1 << n)
+            .map(|bits: i32| (1..n + 1).map(|v| if bits >> (v - 1) & 1 == 1 { v } else { -v }).collect())
+            .collect()
+    }
+}
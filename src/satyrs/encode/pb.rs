@@ -0,0 +1,392 @@
+//! Pseudo-Boolean constraints: weighted sums of literals compared against a constant, such as
+//! `3x1 + 2x2 + 5x3 <= 6`.
+//!
+//! Every constraint is first normalized into the form `sum w_i * l_i <= k` with `w_i > 0`, by
+//! folding negative coefficients into the negated literal and splitting equalities into two
+//! inequalities. The encoders below only ever see that normal form.
+
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+
+use satyrs::cnf::CNF;
+use super::contradiction;
+
+/// Which circuit to use for comparing the weighted sum against the bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PbEncoding {
+    /// Reduced ordered BDD over the terms, sorted by decreasing weight.
+    Bdd,
+    /// Binary adder network followed by a lexicographic comparator. Size is polynomial in the
+    /// number of bits of the weights, but propagates poorly.
+    Adder,
+    /// Joshi, Martins and Manquinho's generalized totalizer: a totalizer whose nodes count
+    /// distinct achievable weight sums rather than true literals.
+    GeneralizedTotalizer,
+}
+
+/// Comparison between the weighted sum and the right-hand side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Le,
+    Ge,
+    Eq,
+}
+
+/// Constrain `sum w * lit` over `terms` (pairs of coefficient and DIMACS literal) to stand in
+/// `relation` to `rhs`. Coefficients may be negative, and literals may repeat.
+pub fn pseudo_boolean(cnf: &mut CNF,
+                      terms: &[(i64, i32)],
+                      relation: Relation,
+                      rhs: i64,
+                      encoding: PbEncoding) {
+    match relation {
+        Relation::Le => at_most_pb(cnf, terms, rhs, encoding),
+        Relation::Ge => {
+            let negated: Vec<(i64, i32)> = terms.iter().map(|&(w, l)| (-w, l)).collect();
+            at_most_pb(cnf, &negated, -rhs, encoding);
+        }
+        Relation::Eq => {
+            pseudo_boolean(cnf, terms, Relation::Le, rhs, encoding);
+            pseudo_boolean(cnf, terms, Relation::Ge, rhs, encoding);
+        }
+    }
+}
+
+/// Rewrite `sum w * lit <= rhs` into an equivalent constraint with one positive coefficient per
+/// variable, returning the terms and the adjusted bound.
+pub fn normalize(terms: &[(i64, i32)], rhs: i64) -> (Vec<(i64, i32)>, i64) {
+    // Coefficient of each (positive) variable; w * -x is rewritten as w - w * x.
+    let mut coefs: HashMap<i32, i64> = HashMap::new();
+    let mut k = rhs;
+    for &(w, lit) in terms {
+        if lit > 0 {
+            *coefs.entry(lit).or_insert(0) += w;
+        } else {
+            *coefs.entry(-lit).or_insert(0) -= w;
+            k -= w;
+        }
+    }
+    let mut normal = Vec::new();
+    for (var, w) in coefs {
+        if w > 0 {
+            normal.push((w, var));
+        } else if w < 0 {
+            // w * x = w + |w| * -x
+            normal.push((-w, -var));
+            k -= w;
+        }
+    }
+    // Sort by decreasing weight, breaking ties by literal so encodings are deterministic.
+    normal.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    (normal, k)
+}
+
+fn at_most_pb(cnf: &mut CNF, terms: &[(i64, i32)], rhs: i64, encoding: PbEncoding) {
+    let (terms, k) = normalize(terms, rhs);
+    if k < 0 {
+        contradiction(cnf);
+        return;
+    }
+    // Any term that exceeds the bound on its own must be false.
+    let mut kept = Vec::new();
+    for (w, lit) in terms {
+        if w > k {
+            cnf.add_clause(vec![-lit]);
+        } else {
+            kept.push((w, lit));
+        }
+    }
+    if kept.iter().map(|t| t.0).sum::<i64>() <= k {
+        return;
+    }
+    match encoding {
+        PbEncoding::Bdd => bdd(cnf, &kept, k),
+        PbEncoding::Adder => adder(cnf, &kept, k),
+        PbEncoding::GeneralizedTotalizer => generalized_totalizer(cnf, &kept, k),
+    }
+}
+
+// Begin BDD
+
+#[derive(Clone, Copy)]
+enum Node {
+    True,
+    False,
+    Var(i32),
+}
+
+/// The root of the BDD implies that the constraint holds; each node `(i, k)` implies that the
+/// terms from `i` onwards sum to at most `k`.
+fn bdd(cnf: &mut CNF, terms: &[(i64, i32)], k: i64) {
+    let mut suffix = vec![0; terms.len() + 1];
+    for i in (0..terms.len()).rev() {
+        suffix[i] = suffix[i + 1] + terms[i].0;
+    }
+    let mut memo = HashMap::new();
+    match bdd_node(cnf, terms, &suffix, 0, k, &mut memo) {
+        Node::True => {}
+        Node::False => contradiction(cnf),
+        Node::Var(root) => cnf.add_clause(vec![root]),
+    }
+}
+
+fn bdd_node(cnf: &mut CNF,
+            terms: &[(i64, i32)],
+            suffix: &[i64],
+            i: usize,
+            k: i64,
+            memo: &mut HashMap<(usize, i64), Node>)
+            -> Node {
+    if k < 0 {
+        return Node::False;
+    }
+    if suffix[i] <= k {
+        return Node::True;
+    }
+    if let Some(node) = memo.get(&(i, k)) {
+        return *node;
+    }
+    let (w, lit) = terms[i];
+    let hi = bdd_node(cnf, terms, suffix, i + 1, k - w, memo);
+    let lo = bdd_node(cnf, terms, suffix, i + 1, k, memo);
+    let n = cnf.new_var();
+    match hi {
+        Node::True => {}
+        Node::False => cnf.add_clause(vec![-n, -lit]),
+        Node::Var(h) => cnf.add_clause(vec![-n, -lit, h]),
+    }
+    match lo {
+        Node::True => {}
+        Node::False => cnf.add_clause(vec![-n]),
+        Node::Var(l) => cnf.add_clause(vec![-n, l]),
+    }
+    memo.insert((i, k), Node::Var(n));
+    Node::Var(n)
+}
+
+// End BDD
+
+// Begin adder network
+
+/// Sum the weights in binary with full and half adders, bucketing literals by bit position, then
+/// forbid the resulting number from exceeding `k`.
+fn adder(cnf: &mut CNF, terms: &[(i64, i32)], k: i64) {
+    let mut buckets: Vec<Vec<i32>> = Vec::new();
+    for &(w, lit) in terms {
+        let mut bit = 0;
+        while w >> bit > 0 {
+            if w >> bit & 1 == 1 {
+                while buckets.len() <= bit {
+                    buckets.push(Vec::new());
+                }
+                buckets[bit].push(lit);
+            }
+            bit += 1;
+        }
+    }
+
+    // sum[p] is None when bit p of the sum is always zero
+    let mut sum: Vec<Option<i32>> = Vec::new();
+    let mut p = 0;
+    while p < buckets.len() {
+        while buckets[p].len() >= 2 {
+            let a = buckets[p].pop().unwrap();
+            let b = buckets[p].pop().unwrap();
+            let (s, carry) = if let Some(c) = buckets[p].pop() {
+                full_adder(cnf, a, b, c)
+            } else {
+                half_adder(cnf, a, b)
+            };
+            buckets[p].push(s);
+            if buckets.len() <= p + 1 {
+                buckets.push(Vec::new());
+            }
+            buckets[p + 1].push(carry);
+        }
+        sum.push(buckets[p].pop());
+        p += 1;
+    }
+
+    // The sum exceeds k iff for some bit p with k_p = 0 we have s_p = 1, and s agrees with k on
+    // every bit above p. It is enough to require a set bit of k above p to be unset in s.
+    for (p, s_p) in sum.iter().enumerate() {
+        let s_p = match *s_p {
+            Some(s) if k >> p & 1 == 0 => s,
+            _ => continue,
+        };
+        let mut clause = vec![-s_p];
+        let mut satisfied = false;
+        for (q, s_q) in sum.iter().enumerate().skip(p + 1) {
+            if k >> q & 1 == 1 {
+                match *s_q {
+                    Some(s) => clause.push(-s),
+                    None => satisfied = true,
+                }
+            }
+        }
+        if !satisfied {
+            cnf.add_clause(clause);
+        }
+    }
+}
+
+/// Return `(a xor b xor c, majority(a, b, c))`, fully defined.
+pub(crate) fn full_adder(cnf: &mut CNF, a: i32, b: i32, c: i32) -> (i32, i32) {
+    let s = cnf.new_var();
+    let carry = cnf.new_var();
+    for signs in 0..8 {
+        let lit = |x: i32, bit: i32| if signs >> bit & 1 == 1 { -x } else { x };
+        let parity = (signs & 1) ^ (signs >> 1 & 1) ^ (signs >> 2 & 1);
+        // The clause is falsified exactly when each input takes the sign opposite to its literal
+        // here; s must then equal the parity of the inputs that are true.
+        cnf.add_clause(vec![lit(a, 0), lit(b, 1), lit(c, 2), if parity == 1 { s } else { -s }]);
+    }
+    cnf.add_clause(vec![-a, -b, carry]);
+    cnf.add_clause(vec![-a, -c, carry]);
+    cnf.add_clause(vec![-b, -c, carry]);
+    cnf.add_clause(vec![a, b, -carry]);
+    cnf.add_clause(vec![a, c, -carry]);
+    cnf.add_clause(vec![b, c, -carry]);
+    (s, carry)
+}
+
+/// Return `(a xor b, a and b)`, fully defined.
+pub(crate) fn half_adder(cnf: &mut CNF, a: i32, b: i32) -> (i32, i32) {
+    let s = cnf.new_var();
+    let carry = cnf.new_var();
+    cnf.add_clause(vec![-a, -b, -s]);
+    cnf.add_clause(vec![a, b, -s]);
+    cnf.add_clause(vec![-a, b, s]);
+    cnf.add_clause(vec![a, -b, s]);
+    cnf.add_clause(vec![-a, -b, carry]);
+    cnf.add_clause(vec![a, -carry]);
+    cnf.add_clause(vec![b, -carry]);
+    (s, carry)
+}
+
+// End adder network
+
+// Begin generalized totalizer
+
+/// Each node maps every achievable partial sum (capped at `k + 1`) to a variable implied by any
+/// assignment reaching at least that sum; the root's `k + 1` output is forced false.
+fn generalized_totalizer(cnf: &mut CNF, terms: &[(i64, i32)], k: i64) {
+    let root = gte_node(cnf, terms, k + 1);
+    if let Some(overflow) = root.get(&(k + 1)) {
+        cnf.add_clause(vec![-*overflow]);
+    }
+}
+
+fn gte_node(cnf: &mut CNF, terms: &[(i64, i32)], cap: i64) -> BTreeMap<i64, i32> {
+    if terms.len() == 1 {
+        let mut leaf = BTreeMap::new();
+        leaf.insert(cmp::min(terms[0].0, cap), terms[0].1);
+        return leaf;
+    }
+    let (left, right) = terms.split_at(terms.len() / 2);
+    let a = gte_node(cnf, left, cap);
+    let b = gte_node(cnf, right, cap);
+    let mut outputs: BTreeMap<i64, i32> = BTreeMap::new();
+    // 0 stands for "no literal needed" on that side
+    let with_zero = |m: &BTreeMap<i64, i32>| {
+        let mut v: Vec<(i64, Option<i32>)> = vec![(0, None)];
+        v.extend(m.iter().map(|(w, l)| (*w, Some(*l))));
+        v
+    };
+    for (wa, la) in with_zero(&a) {
+        for &(wb, lb) in with_zero(&b).iter() {
+            if wa + wb == 0 {
+                continue;
+            }
+            let sum = cmp::min(wa + wb, cap);
+            let out = *outputs.entry(sum).or_insert_with(|| cnf.new_var());
+            let mut clause = vec![out];
+            clause.extend(la.map(|l| -l));
+            clause.extend(lb.map(|l| -l));
+            cnf.add_clause(clause);
+        }
+    }
+    outputs
+}
+
+// End generalized totalizer
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::CNF;
+    use satyrs::encode::tests::{assignments, satisfiable_with};
+
+    use super::*;
+
+    const ENCODINGS: [PbEncoding; 3] = [PbEncoding::Bdd,
+                                        PbEncoding::Adder,
+                                        PbEncoding::GeneralizedTotalizer];
+
+    fn check(n: i32, terms: &[(i64, i32)], relation: Relation, rhs: i64) {
+        for encoding in ENCODINGS.iter() {
+            let mut cnf = CNF::new(n, 0);
+            pseudo_boolean(&mut cnf, terms, relation, rhs, *encoding);
+            for assn in assignments(n) {
+                let value: i64 = terms.iter()
+                                      .filter(|&&(_, l)| assn.contains(&l))
+                                      .map(|&(w, _)| w)
+                                      .sum();
+                let holds = match relation {
+                    Relation::Le => value <= rhs,
+                    Relation::Ge => value >= rhs,
+                    Relation::Eq => value == rhs,
+                };
+                assert_eq!(satisfiable_with(&cnf, &assn),
+                           holds,
+                           "{:?} {:?} {} under {:?}",
+                           encoding,
+                           relation,
+                           rhs,
+                           assn);
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_at_most() {
+        for rhs in -1..12 {
+            check(3, &[(3, 1), (2, 2), (5, 3)], Relation::Le, rhs);
+        }
+    }
+
+    #[test]
+    fn weighted_at_least() {
+        for rhs in 0..16 {
+            check(4, &[(3, 1), (2, 2), (5, 3), (4, 4)], Relation::Ge, rhs);
+        }
+    }
+
+    #[test]
+    fn equality() {
+        for rhs in 0..11 {
+            check(4, &[(1, 1), (2, 2), (3, 3), (4, 4)], Relation::Eq, rhs);
+        }
+    }
+
+    #[test]
+    fn negative_coefficients_and_literals() {
+        for rhs in -8..8 {
+            check(4, &[(-3, 1), (2, -2), (5, 3), (-1, -4)], Relation::Le, rhs);
+        }
+    }
+
+    #[test]
+    fn repeated_variables() {
+        for rhs in -3..6 {
+            check(2, &[(2, 1), (3, -1), (1, 2), (1, 2)], Relation::Le, rhs);
+        }
+    }
+
+    #[test]
+    fn normalize_folds_negatives() {
+        let (terms, k) = normalize(&[(-3, 1), (2, 2)], 1);
+        // -3x1 + 2x2 <= 1  ==  3(-x1) + 2x2 <= 4
+        assert_eq!(terms, vec![(3, -1), (2, 2)]);
+        assert_eq!(k, 4);
+    }
+}
@@ -0,0 +1,87 @@
+//! Sharing learned clauses between solvers running at the same time.
+//!
+//! An `Exchange` is a bounded buffer that keeps only the most recent clauses. CDCL solvers publish
+//! to it and read from it; `parallel` sets one up for the solvers it races.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// A bounded buffer of learned clauses, each tagged with the solver that learned it.
+pub struct Exchange {
+    capacity: usize,
+    max_len: usize,
+    buffer: Mutex<Buffer>,
+}
+
+struct Buffer {
+    /// Sequence number of the next clause to be published.
+    next: u64,
+    clauses: VecDeque<(u64, usize, Vec<i32>)>,
+}
+
+impl Exchange {
+    /// Keep at most `capacity` clauses, each at most `max_len` literals long.
+    pub fn new(capacity: usize, max_len: usize) -> Exchange {
+        Exchange {
+            capacity,
+            max_len,
+            buffer: Mutex::new(Buffer {
+                next: 0,
+                clauses: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Publish a clause of DIMACS literals learned by solver `origin`. Clauses that are too long
+    /// are ignored, and once the buffer is full the oldest clause makes room.
+    pub fn export(&self, origin: usize, clause: &[i32]) {
+        if clause.len() > self.max_len || self.capacity == 0 {
+            return;
+        }
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.clauses.len() == self.capacity {
+            buffer.clauses.pop_front();
+        }
+        let seq = buffer.next;
+        buffer.next += 1;
+        buffer.clauses.push_back((seq, origin, clause.to_vec()));
+    }
+
+    /// The clauses published by solvers other than `origin` since `cursor`, which is moved past
+    /// them. Clauses pushed out of the buffer in the meantime are lost.
+    pub fn import(&self, origin: usize, cursor: &mut u64) -> Vec<Vec<i32>> {
+        let buffer = self.buffer.lock().unwrap();
+        let clauses = buffer.clauses
+                            .iter()
+                            .filter(|&&(seq, from, _)| seq >= *cursor && from != origin)
+                            .map(|(_, _, clause)| clause.clone())
+                            .collect();
+        *cursor = buffer.next;
+        clauses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exchange_is_bounded() {
+        let exchange = Exchange::new(2, 3);
+        let mut cursor = 0;
+        exchange.export(0, &[1, 2]);
+        exchange.export(0, &[1, 2, 3, 4]);
+        exchange.export(1, &[-1]);
+        exchange.export(0, &[3]);
+        // The first clause was pushed out and the long one never got in
+        assert_eq!(exchange.import(2, &mut cursor), vec![vec![-1], vec![3]]);
+        assert!(exchange.import(2, &mut cursor).is_empty());
+        // Solvers don't get their own clauses back
+        let mut cursor = 0;
+        assert_eq!(exchange.import(1, &mut cursor), vec![vec![3]]);
+    }
+}
@@ -0,0 +1,466 @@
+//! Arbitrary Boolean formulas, converted to clauses by the Tseitin or Plaisted-Greenbaum
+//! transformation.
+//!
+//! Formulas live in a `FormulaBuilder`, which hash-conses them: building the same subformula twice
+//! gives back the same `Formula` handle, so it is only ever defined once in the CNF. Constants
+//! and double negations are simplified away as formulas are built.
+
+use std::collections::HashMap;
+
+use satyrs::cnf::{Assignment, CNF};
+
+/// Handle to a formula stored in a `FormulaBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Formula(usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node {
+    Const(bool),
+    Var(i32),
+    Not(Formula),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+    Xor(Formula, Formula),
+    Implies(Formula, Formula),
+    Iff(Formula, Formula),
+    Ite(Formula, Formula, Formula),
+}
+
+/// How to turn a formula into clauses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transformation {
+    /// Every subformula gets a variable that is equivalent to it.
+    Tseitin,
+    /// Only the implications in the direction(s) a subformula is actually used are emitted,
+    /// roughly halving the clauses. The result is equisatisfiable but the fresh variables are no
+    /// longer exact definitions.
+    PlaistedGreenbaum,
+}
+
+/// A fresh variable standing for a subformula, and which polarities have been defined so far.
+struct Definition {
+    var: i32,
+    pos: bool,
+    neg: bool,
+}
+
+/// Builds formulas and writes their clauses into a CNF. The variables defined for subformulas
+/// are remembered so that each is only defined once, which ties the builder to the CNF it first
+/// writes into; call `clear_definitions` before writing into another.
+pub struct FormulaBuilder {
+    nodes: Vec<Node>,
+    index: HashMap<Node, Formula>,
+    definitions: HashMap<Formula, Definition>,
+    fresh: Vec<i32>,
+}
+
+impl FormulaBuilder {
+    pub fn new() -> FormulaBuilder {
+        FormulaBuilder {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            definitions: HashMap::new(),
+            fresh: Vec::new(),
+        }
+    }
+
+    fn intern(&mut self, node: Node) -> Formula {
+        if let Some(f) = self.index.get(&node) {
+            return *f;
+        }
+        let f = Formula(self.nodes.len());
+        self.nodes.push(node.clone());
+        self.index.insert(node, f);
+        f
+    }
+
+    fn constant_of(&self, f: Formula) -> Option<bool> {
+        match self.nodes[f.0] {
+            Node::Const(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn constant(&mut self, value: bool) -> Formula {
+        self.intern(Node::Const(value))
+    }
+
+    /// The DIMACS variable `v`, which must already exist in the CNF the formula is added to.
+    pub fn var(&mut self, v: i32) -> Formula {
+        assert!(v > 0, "variables are positive");
+        self.intern(Node::Var(v))
+    }
+
+    /// A DIMACS literal: `var(l)` or its negation.
+    pub fn lit(&mut self, l: i32) -> Formula {
+        let v = self.var(l.abs());
+        if l < 0 { self.not(v) } else { v }
+    }
+
+    pub fn not(&mut self, f: Formula) -> Formula {
+        match self.nodes[f.0] {
+            Node::Const(b) => self.constant(!b),
+            Node::Not(g) => g,
+            _ => self.intern(Node::Not(f)),
+        }
+    }
+
+    pub fn and(&mut self, fs: Vec<Formula>) -> Formula {
+        self.nary(fs, true)
+    }
+
+    pub fn or(&mut self, fs: Vec<Formula>) -> Formula {
+        self.nary(fs, false)
+    }
+
+    /// `And` when `conjunction`, otherwise `Or`. Constants equal to the identity of the operator
+    /// are dropped, and the other constant absorbs the whole formula.
+    fn nary(&mut self, fs: Vec<Formula>, conjunction: bool) -> Formula {
+        let identity = conjunction;
+        let mut operands: Vec<Formula> = Vec::new();
+        for f in fs {
+            match self.constant_of(f) {
+                Some(b) if b == identity => {}
+                Some(_) => return self.constant(!identity),
+                None => {
+                    if !operands.contains(&f) {
+                        operands.push(f);
+                    }
+                }
+            }
+        }
+        match operands.len() {
+            0 => self.constant(identity),
+            1 => operands[0],
+            _ => {
+                if conjunction {
+                    self.intern(Node::And(operands))
+                } else {
+                    self.intern(Node::Or(operands))
+                }
+            }
+        }
+    }
+
+    pub fn xor(&mut self, a: Formula, b: Formula) -> Formula {
+        match (self.constant_of(a), self.constant_of(b)) {
+            (Some(x), _) => if x { self.not(b) } else { b },
+            (_, Some(y)) => if y { self.not(a) } else { a },
+            _ => self.intern(Node::Xor(a, b)),
+        }
+    }
+
+    pub fn implies(&mut self, a: Formula, b: Formula) -> Formula {
+        match (self.constant_of(a), self.constant_of(b)) {
+            (Some(x), _) => if x { b } else { self.constant(true) },
+            (_, Some(y)) => if y { self.constant(true) } else { self.not(a) },
+            _ => self.intern(Node::Implies(a, b)),
+        }
+    }
+
+    pub fn iff(&mut self, a: Formula, b: Formula) -> Formula {
+        match (self.constant_of(a), self.constant_of(b)) {
+            (Some(x), _) => if x { b } else { self.not(b) },
+            (_, Some(y)) => if y { a } else { self.not(a) },
+            _ => self.intern(Node::Iff(a, b)),
+        }
+    }
+
+    /// If `c` then `a` else `b`.
+    pub fn ite(&mut self, c: Formula, a: Formula, b: Formula) -> Formula {
+        match self.constant_of(c) {
+            Some(true) => a,
+            Some(false) => b,
+            None if a == b => a,
+            None => self.intern(Node::Ite(c, a, b)),
+        }
+    }
+
+    /// Evaluate `f` under a full assignment, indexed by variable - 1.
+    pub fn eval(&self, f: Formula, assn: &Assignment) -> bool {
+        match self.nodes[f.0] {
+            Node::Const(b) => b,
+            Node::Var(v) => assn[(v - 1) as usize],
+            Node::Not(g) => !self.eval(g, assn),
+            Node::And(ref fs) => fs.iter().all(|g| self.eval(*g, assn)),
+            Node::Or(ref fs) => fs.iter().any(|g| self.eval(*g, assn)),
+            Node::Xor(a, b) => self.eval(a, assn) != self.eval(b, assn),
+            Node::Implies(a, b) => !self.eval(a, assn) || self.eval(b, assn),
+            Node::Iff(a, b) => self.eval(a, assn) == self.eval(b, assn),
+            Node::Ite(c, a, b) => {
+                if self.eval(c, assn) {
+                    self.eval(a, assn)
+                } else {
+                    self.eval(b, assn)
+                }
+            }
+        }
+    }
+
+    /// Variables introduced to stand for subformulas since the last `clear_definitions`, in order
+    /// of creation.
+    pub fn fresh_vars(&self) -> &[i32] {
+        &self.fresh
+    }
+
+    /// Forget the variables defined for subformulas, so that the next `assert` or `literal` can
+    /// write into a different CNF. Formulas built so far stay valid.
+    pub fn clear_definitions(&mut self) {
+        self.definitions.clear();
+        self.fresh.clear();
+    }
+
+    /// Add `f` to `cnf` as a constraint that must hold.
+    pub fn assert(&mut self, cnf: &mut CNF, f: Formula, transformation: Transformation) {
+        match self.constant_of(f) {
+            Some(true) => {}
+            Some(false) => {
+                let v = cnf.new_var();
+                cnf.add_clause(vec![v]);
+                cnf.add_clause(vec![-v]);
+            }
+            None => {
+                let lit = self.literal(cnf, f, transformation);
+                cnf.add_clause(vec![lit]);
+            }
+        }
+    }
+
+    /// Define `f` in `cnf` and return a DIMACS literal standing for it. Under Tseitin the literal
+    /// is equivalent to `f`; under Plaisted-Greenbaum it only implies `f`.
+    pub fn literal(&mut self, cnf: &mut CNF, f: Formula, transformation: Transformation) -> i32 {
+        let neg = transformation == Transformation::Tseitin;
+        self.define(cnf, f, true, neg)
+    }
+
+    /// Return a literal for `f`, emitting the clauses for `lit -> f` if `pos` and for `f -> lit`
+    /// if `neg`, unless they have been emitted before.
+    fn define(&mut self, cnf: &mut CNF, f: Formula, pos: bool, neg: bool) -> i32 {
+        let node = self.nodes[f.0].clone();
+        match node {
+            Node::Var(v) => return v,
+            Node::Not(g) => return -self.define(cnf, g, neg, pos),
+            Node::Const(_) => {
+                // Only reachable through `literal` on a constant; give it a pinned variable.
+                let value = self.constant_of(f) == Some(true);
+                let v = cnf.new_var();
+                self.fresh.push(v);
+                cnf.add_clause(vec![if value { v } else { -v }]);
+                return v;
+            }
+            _ => {}
+        }
+
+        if !self.definitions.contains_key(&f) {
+            let var = cnf.new_var();
+            self.fresh.push(var);
+            self.definitions.insert(f, Definition { var, pos: false, neg: false });
+        }
+        let (t, need_pos, need_neg) = {
+            let def = self.definitions.get_mut(&f).unwrap();
+            let need = (pos && !def.pos, neg && !def.neg);
+            def.pos |= pos;
+            def.neg |= neg;
+            (def.var, need.0, need.1)
+        };
+        if !need_pos && !need_neg {
+            return t;
+        }
+        let either = need_pos || need_neg;
+
+        match node {
+            Node::And(fs) => {
+                let xs: Vec<i32> = fs.iter().map(|g| self.define(cnf, *g, need_pos, need_neg)).collect();
+                if need_pos {
+                    for x in &xs {
+                        cnf.add_clause(vec![-t, *x]);
+                    }
+                }
+                if need_neg {
+                    let mut clause: Vec<i32> = xs.iter().map(|x| -x).collect();
+                    clause.push(t);
+                    cnf.add_clause(clause);
+                }
+            }
+            Node::Or(fs) => {
+                let xs: Vec<i32> = fs.iter().map(|g| self.define(cnf, *g, need_pos, need_neg)).collect();
+                if need_pos {
+                    let mut clause = xs.clone();
+                    clause.push(-t);
+                    cnf.add_clause(clause);
+                }
+                if need_neg {
+                    for x in &xs {
+                        cnf.add_clause(vec![t, -x]);
+                    }
+                }
+            }
+            Node::Xor(a, b) => {
+                let a = self.define(cnf, a, either, either);
+                let b = self.define(cnf, b, either, either);
+                if need_pos {
+                    cnf.add_clause(vec![-t, a, b]);
+                    cnf.add_clause(vec![-t, -a, -b]);
+                }
+                if need_neg {
+                    cnf.add_clause(vec![t, -a, b]);
+                    cnf.add_clause(vec![t, a, -b]);
+                }
+            }
+            Node::Implies(a, b) => {
+                let a = self.define(cnf, a, need_neg, need_pos);
+                let b = self.define(cnf, b, need_pos, need_neg);
+                if need_pos {
+                    cnf.add_clause(vec![-t, -a, b]);
+                }
+                if need_neg {
+                    cnf.add_clause(vec![t, a]);
+                    cnf.add_clause(vec![t, -b]);
+                }
+            }
+            Node::Iff(a, b) => {
+                let a = self.define(cnf, a, either, either);
+                let b = self.define(cnf, b, either, either);
+                if need_pos {
+                    cnf.add_clause(vec![-t, -a, b]);
+                    cnf.add_clause(vec![-t, a, -b]);
+                }
+                if need_neg {
+                    cnf.add_clause(vec![t, a, b]);
+                    cnf.add_clause(vec![t, -a, -b]);
+                }
+            }
+            Node::Ite(c, a, b) => {
+                let c = self.define(cnf, c, either, either);
+                let a = self.define(cnf, a, need_pos, need_neg);
+                let b = self.define(cnf, b, need_pos, need_neg);
+                if need_pos {
+                    cnf.add_clause(vec![-t, -c, a]);
+                    cnf.add_clause(vec![-t, c, b]);
+                }
+                if need_neg {
+                    cnf.add_clause(vec![t, -c, -a]);
+                    cnf.add_clause(vec![t, c, -b]);
+                }
+            }
+            Node::Const(_) | Node::Var(_) | Node::Not(_) => unreachable!(),
+        }
+        t
+    }
+}
+
+impl Default for FormulaBuilder {
+    fn default() -> FormulaBuilder {
+        FormulaBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::CNF;
+    use satyrs::encode::tests::{assignments, satisfiable_with};
+
+    use super::*;
+
+    const TRANSFORMATIONS: [Transformation; 2] = [Transformation::Tseitin,
+                                                  Transformation::PlaistedGreenbaum];
+
+    /// One formula of every kind over variables 1 to 3.
+    fn formulas(b: &mut FormulaBuilder) -> Vec<Formula> {
+        let (x, y, z) = (b.var(1), b.var(2), b.var(3));
+        let nx = b.not(x);
+        let xy = b.and(vec![x, y]);
+        let y_or_z = b.or(vec![y, z]);
+        let xor = b.xor(x, y_or_z);
+        let imp = b.implies(xy, z);
+        let iff = b.iff(nx, y_or_z);
+        let ite = b.ite(x, xor, iff);
+        let not_ite = b.not(ite);
+        let nested = b.or(vec![not_ite, imp, xy]);
+        let nested = b.not(nested);
+        vec![xy, y_or_z, xor, imp, iff, ite, not_ite, nested]
+    }
+
+    #[test]
+    fn transformations_preserve_models() {
+        for transformation in TRANSFORMATIONS.iter() {
+            let mut b = FormulaBuilder::new();
+            for f in formulas(&mut b) {
+                let mut cnf = CNF::new(3, 0);
+                b.clear_definitions();
+                b.assert(&mut cnf, f, *transformation);
+                for assn in assignments(3) {
+                    let bools: Vec<bool> = assn.iter().map(|l| *l > 0).collect();
+                    assert_eq!(satisfiable_with(&cnf, &assn), b.eval(f, &bools));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tseitin_literal_is_equivalent() {
+        let mut b = FormulaBuilder::new();
+        for f in formulas(&mut b) {
+            let mut cnf = CNF::new(3, 0);
+            b.clear_definitions();
+            let lit = b.literal(&mut cnf, f, Transformation::Tseitin);
+            for mut assn in assignments(3) {
+                let bools: Vec<bool> = assn.iter().map(|l| *l > 0).collect();
+                assn.push(if b.eval(f, &bools) { -lit } else { lit });
+                assert!(!satisfiable_with(&cnf, &assn));
+            }
+        }
+    }
+
+    #[test]
+    fn shares_subformulas() {
+        let mut b = FormulaBuilder::new();
+        let (x, y) = (b.var(1), b.var(2));
+        let first = b.and(vec![x, y]);
+        let second = b.and(vec![x, y]);
+        assert_eq!(first, second);
+
+        let mut cnf = CNF::new(2, 0);
+        let either = b.or(vec![first, second]);
+        assert_eq!(either, first);
+        let nfirst = b.not(first);
+        let xor = b.xor(first, y);
+        let f = b.or(vec![nfirst, xor]);
+        b.assert(&mut cnf, f, Transformation::Tseitin);
+        // One variable each for the and, the xor and the or
+        assert_eq!(b.fresh_vars(), &[3, 4, 5]);
+        assert_eq!(cnf.nvar, 5);
+    }
+
+    #[test]
+    fn plaisted_greenbaum_is_smaller() {
+        let mut tseitin = CNF::new(3, 0);
+        let mut pg = CNF::new(3, 0);
+        let mut b = FormulaBuilder::new();
+        let f = formulas(&mut b)[7];
+        b.assert(&mut tseitin, f, Transformation::Tseitin);
+        let mut b = FormulaBuilder::new();
+        let f = formulas(&mut b)[7];
+        b.assert(&mut pg, f, Transformation::PlaistedGreenbaum);
+        assert!(pg.clauses.len() < tseitin.clauses.len());
+    }
+
+    #[test]
+    fn simplifies_constants() {
+        let mut b = FormulaBuilder::new();
+        let x = b.var(1);
+        let t = b.constant(true);
+        let f = b.constant(false);
+        assert_eq!(b.and(vec![x, t]), x);
+        assert_eq!(b.or(vec![x, t]), t);
+        assert_eq!(b.implies(f, x), t);
+        let nx = b.not(x);
+        assert_eq!(b.xor(x, t), nx);
+        assert_eq!(b.not(nx), x);
+
+        let mut cnf = CNF::new(1, 0);
+        let contradiction = b.and(vec![x, f]);
+        b.assert(&mut cnf, contradiction, Transformation::Tseitin);
+        assert!(!satisfiable_with(&cnf, &[]));
+    }
+}
@@ -0,0 +1,293 @@
+//! Weighted partial MaxSAT: satisfy every hard clause while minimizing the total weight of the
+//! falsified soft clauses.
+//!
+//! Instances are read from WCNF files, in either the classic form (a `p wcnf nvar nclause top`
+//! header, with clauses weighted `top` or more being hard) or the newer form without a header,
+//! where hard clauses start with `h`. Solving is a linear SAT-UNSAT search: every soft clause gets
+//! a relaxation literal, and `satyrs::optimize` minimizes the weight of the relaxed clauses.
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::fs::File;
+
+use satyrs::cnf::{Assignment, CNF};
+use satyrs::encode::pb::PbEncoding;
+use satyrs::optimize;
+
+/// A weighted partial MaxSAT instance, with clauses as DIMACS literals.
+pub struct Wcnf {
+    pub nvar: i32,
+    pub hard: Vec<Vec<i32>>,
+    pub soft: Vec<(i64, Vec<i32>)>,
+}
+
+impl Wcnf {
+    /// Total weight of the soft clauses falsified by `assn`.
+    pub fn cost(&self, assn: &Assignment) -> i64 {
+        self.soft
+            .iter()
+            .filter(|(_, clause)| {
+                !clause.iter().any(|l| assn[(l.abs() - 1) as usize] == (*l > 0))
+            })
+            .map(|&(w, _)| w)
+            .sum()
+    }
+}
+
+fn parse_wcnf(reader: &mut BufReader<File>) -> Result<Wcnf, &'static str> {
+    let mut nvar: i32 = 0;
+    let mut nclause: Option<usize> = None;
+    let mut top: Option<i64> = None;
+    let mut seen_header = false;
+    let mut hard = Vec::new();
+    let mut soft = Vec::new();
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => return Err("could not read file"),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() || words[0] == "c" {
+            continue;
+        }
+        if words[0] == "p" {
+            if seen_header {
+                return Err("duplicate problem statement");
+            }
+            seen_header = true;
+            // Must have format "p wcnf nvar nclause [top]"
+            if words.len() < 4 || words.len() > 5 || words[1] != "wcnf" {
+                return Err("invalid problem statement");
+            }
+            nvar = match words[2].parse() {
+                Ok(n) => n,
+                Err(_) => return Err("invalid number of variables"),
+            };
+            nclause = match words[3].parse() {
+                Ok(n) => Some(n),
+                Err(_) => return Err("invalid number of clauses"),
+            };
+            if words.len() == 5 {
+                top = match words[4].parse() {
+                    Ok(t) => Some(t),
+                    Err(_) => return Err("invalid top weight"),
+                };
+            }
+            continue;
+        }
+
+        let is_hard;
+        let weight: i64;
+        if words[0] == "h" {
+            is_hard = true;
+            weight = 0;
+        } else {
+            weight = match words[0].parse() {
+                Ok(w) if w > 0 => w,
+                _ => return Err("invalid clause weight"),
+            };
+            is_hard = top.is_some_and(|t| weight >= t);
+        }
+        let mut clause = Vec::new();
+        for word in &words[1..] {
+            let lit: i32 = match word.parse() {
+                Ok(l) => l,
+                Err(_) => return Err("invalid literal"),
+            };
+            if lit == 0 {
+                break;
+            }
+            // `abs` would overflow on i32::MIN
+            if seen_header && lit.unsigned_abs() > nvar.max(0) as u32 {
+                return Err("variable out of range");
+            }
+            if lit == i32::MIN {
+                return Err("invalid literal");
+            }
+            clause.push(lit);
+        }
+        if is_hard {
+            hard.push(clause);
+        } else {
+            soft.push((weight, clause));
+        }
+    }
+    // Only the classic format says how many clauses to expect
+    if let Some(n) = nclause {
+        if hard.len() + soft.len() > n {
+            return Err("too many clauses in file");
+        }
+        if hard.len() + soft.len() < n {
+            return Err("too few clauses in file");
+        }
+    }
+    // The newer format has no header, so count the variables ourselves.
+    let used = hard.iter()
+                   .chain(soft.iter().map(|(_, c)| c))
+                   .flat_map(|c| c.iter())
+                   .map(|l| l.abs())
+                   .max()
+                   .unwrap_or(0);
+    if used > nvar {
+        nvar = used;
+    }
+    Ok(Wcnf { nvar, hard, soft })
+}
+
+pub fn parse_wcnf_file(f: File) -> Result<Wcnf, &'static str> {
+    let mut reader = BufReader::new(f);
+    parse_wcnf(&mut reader)
+}
+
+/// Find an optimal solution to `wcnf`, returning its cost and an assignment to the original
+/// variables, or `None` if the hard clauses are unsatisfiable.
+pub fn solve(wcnf: &Wcnf, encoding: PbEncoding, verbose: bool) -> Option<(i64, Assignment)> {
+    if wcnf.hard.iter().any(|c| c.is_empty()) {
+        return None;
+    }
+    let mut base = CNF::new(wcnf.nvar, 0);
+    for clause in &wcnf.hard {
+        base.add_clause(clause.clone());
+    }
+    // Relaxation literals: true when the soft clause is allowed to be falsified. A unit soft
+    // clause can use its own negation. Empty soft clauses are always falsified.
+    let mut relaxed: Vec<(i64, i32)> = Vec::new();
+    for &(w, ref clause) in &wcnf.soft {
+        match clause.len() {
+            0 => {}
+            1 => relaxed.push((w, -clause[0])),
+            _ => {
+                let r = base.new_var();
+                let mut clause = clause.clone();
+                clause.push(r);
+                base.add_clause(clause);
+                relaxed.push((w, r));
+            }
+        }
+    }
+
+    // The relaxed weight only bounds the cost, as for the result below, and a recount can be
+    // worse than an earlier one, so only report a new best
+    let mut best = i64::MAX;
+    let solution = optimize::minimize(&base, &relaxed, encoding, None, |_, assn| {
+        let cost = wcnf.cost(assn);
+        if verbose && cost < best {
+            println!("o {}", cost);
+        }
+        best = best.min(cost);
+    });
+    solution.map(|s| {
+        let mut assn = s.assignment;
+        assn.truncate(wcnf.nvar as usize);
+        // A relaxation literal may be set even though its clause holds, so recount.
+        (wcnf.cost(&assn), assn)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use std::fs::File;
+    use std::io::prelude::*;
+    use std::io::SeekFrom;
+
+    use satyrs::encode::PbEncoding;
+    use super::*;
+
+    #[test]
+    fn parses_classic_format() {
+        let tmpfile = create_tempfile!("
+            c classic
+            p wcnf 3 4 10
+            10 1 2 0
+            3 -1 0
+            5 -2 3 0
+            12 -3 0
+        ");
+        let wcnf = parse_wcnf_file(tmpfile).unwrap();
+        assert_eq!(wcnf.nvar, 3);
+        assert_eq!(wcnf.hard, vec![vec![1, 2], vec![-3]]);
+        assert_eq!(wcnf.soft, vec![(3, vec![-1]), (5, vec![-2, 3])]);
+    }
+
+    #[test]
+    fn parses_new_format() {
+        let tmpfile = create_tempfile!("
+            c new
+            h 1 2 0
+            3 -1 0
+            5 -4 0
+        ");
+        let wcnf = parse_wcnf_file(tmpfile).unwrap();
+        assert_eq!(wcnf.nvar, 4);
+        assert_eq!(wcnf.hard, vec![vec![1, 2]]);
+        assert_eq!(wcnf.soft, vec![(3, vec![-1]), (5, vec![-4])]);
+    }
+
+    #[test]
+    fn variable_out_of_range() {
+        let tmpfile = create_tempfile!("
+            p wcnf 2 1 10
+            10 1 3 0
+        ");
+        assert_eq!(parse_wcnf_file(tmpfile).err(), Some("variable out of range"));
+        let tmpfile = create_tempfile!("
+            p wcnf 2 1 10
+            10 1 -2147483648 0
+        ");
+        assert_eq!(parse_wcnf_file(tmpfile).err(), Some("variable out of range"));
+        // Without a header there is no range, but no variable can be that large either
+        let tmpfile = create_tempfile!("
+            h 1 -2147483648 0
+        ");
+        assert_eq!(parse_wcnf_file(tmpfile).err(), Some("invalid literal"));
+    }
+
+    #[test]
+    fn checks_clause_count() {
+        let tmpfile = create_tempfile!("
+            p wcnf 2 3 10
+            10 1 2 0
+            3 -1 0
+        ");
+        assert_eq!(parse_wcnf_file(tmpfile).err(), Some("too few clauses in file"));
+        let tmpfile = create_tempfile!("
+            p wcnf 2 1 10
+            10 1 2 0
+            3 -1 0
+        ");
+        assert_eq!(parse_wcnf_file(tmpfile).err(), Some("too many clauses in file"));
+    }
+
+    #[test]
+    fn finds_optimum() {
+        let tmpfile = create_tempfile!("
+            h 1 2 0
+            3 -1 0
+            5 -2 0
+            1 1 0
+            2 -1 -2 3 0
+            4 -3 0
+        ");
+        let wcnf = parse_wcnf_file(tmpfile).unwrap();
+        let encodings = [PbEncoding::Bdd, PbEncoding::Adder, PbEncoding::GeneralizedTotalizer];
+        for encoding in encodings.iter() {
+            let (cost, assn) = solve(&wcnf, *encoding, false).unwrap();
+            assert_eq!(cost, 3);
+            assert_eq!(wcnf.cost(&assn), 3);
+            assert_eq!(&assn[..2], &[true, false]);
+        }
+    }
+
+    #[test]
+    fn unsatisfiable_hard_clauses() {
+        let tmpfile = create_tempfile!("
+            h 1 0
+            h -1 0
+            1 1 0
+        ");
+        let wcnf = parse_wcnf_file(tmpfile).unwrap();
+        assert!(solve(&wcnf, PbEncoding::Bdd, false).is_none());
+    }
+}
@@ -2,3 +2,17 @@
 pub mod cnf;
 pub mod dpll;
 pub mod heuristics;
+pub mod encode;
+pub mod opb;
+pub mod formula;
+pub mod maxsat;
+pub mod optimize;
+pub mod qbf;
+pub mod sls;
+pub mod portfolio;
+pub mod exchange;
+pub mod cdcl;
+pub mod parallel;
+pub mod lookahead;
+pub mod cube;
+pub mod preprocess;
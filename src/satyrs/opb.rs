@@ -0,0 +1,232 @@
+//! Parser for the OPB format of the pseudo-Boolean competitions. Each constraint is encoded into
+//! clauses with one of the encoders in `satyrs::encode::pb`.
+//!
+//! ```text
+//! * #variable= 3 #constraint= 2
+//! min: +1 x1 +2 x2 ;
+//! +3 x1 +2 x2 +5 x3 <= 6 ;
+//! +1 x1 +1 ~x3 >= 1 ;
+//! ```
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::fs::File;
+
+use satyrs::cnf::CNF;
+use satyrs::encode::pb::{PbEncoding, Relation, pseudo_boolean};
+
+/// A parsed OPB instance. Variables past `nvar` in `cnf` are auxiliary variables introduced by
+/// the encoder.
+pub struct Opb {
+    pub cnf: CNF,
+    pub nvar: i32,
+    /// Terms of the `min:` objective, if any, as (coefficient, DIMACS literal) pairs.
+    pub objective: Option<Vec<(i64, i32)>>,
+}
+
+struct Constraint {
+    terms: Vec<(i64, i32)>,
+    relation: Relation,
+    rhs: i64,
+}
+
+/// Parse a literal such as `x3` or `~x3` into a DIMACS literal.
+fn parse_literal(token: &str) -> Result<i32, &'static str> {
+    let (negated, name) = match token.strip_prefix('~') {
+        Some(name) => (true, name),
+        None => (false, token),
+    };
+    let index = match name.strip_prefix('x') {
+        Some(index) => index,
+        None => return Err("invalid literal"),
+    };
+    let var: i32 = match index.parse() {
+        Ok(v) if v > 0 => v,
+        _ => return Err("invalid variable index"),
+    };
+    Ok(if negated { -var } else { var })
+}
+
+/// Parse a sequence of `coefficient literal` pairs.
+fn parse_terms(tokens: &[&str]) -> Result<Vec<(i64, i32)>, &'static str> {
+    if !tokens.len().is_multiple_of(2) {
+        return Err("expected coefficient and literal pairs");
+    }
+    let mut terms = Vec::new();
+    for pair in tokens.chunks(2) {
+        let coef: i64 = match pair[0].parse() {
+            Ok(c) => c,
+            Err(_) => return Err("invalid coefficient"),
+        };
+        terms.push((coef, parse_literal(pair[1])?));
+    }
+    Ok(terms)
+}
+
+/// Parse the `* #variable= n` header comment, if this is one.
+fn parse_header(line: &str) -> Option<i32> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    words.iter()
+         .position(|w| *w == "#variable=")
+         .and_then(|i| words.get(i + 1))
+         .and_then(|n| n.parse().ok())
+}
+
+fn parse_opb(reader: &mut BufReader<File>,
+             encoding: PbEncoding)
+             -> Result<Opb, &'static str> {
+    let mut nvar: i32 = 0;
+    let mut body = String::new();
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => return Err("could not read file"),
+        };
+        let line = line.trim();
+        if line.starts_with('*') {
+            if let Some(n) = parse_header(line) {
+                nvar = n;
+            }
+            continue;
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+
+    // Statements may span lines, and are terminated by semicolons.
+    let mut objective = None;
+    let mut constraints = Vec::new();
+    for statement in body.split(';') {
+        let tokens: Vec<&str> = statement.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        if tokens[0] == "min:" {
+            if objective.is_some() {
+                return Err("duplicate objective");
+            }
+            objective = Some(parse_terms(&tokens[1..])?);
+            continue;
+        }
+        if tokens[0].ends_with(':') {
+            return Err("unsupported objective");
+        }
+        let n = tokens.len();
+        if n < 2 {
+            return Err("incomplete constraint");
+        }
+        let relation = match tokens[n - 2] {
+            ">=" => Relation::Ge,
+            "<=" => Relation::Le,
+            "=" => Relation::Eq,
+            _ => return Err("unknown relation"),
+        };
+        let rhs: i64 = match tokens[n - 1].parse() {
+            Ok(r) => r,
+            Err(_) => return Err("invalid right-hand side"),
+        };
+        constraints.push(Constraint {
+            terms: parse_terms(&tokens[..n - 2])?,
+            relation,
+            rhs,
+        });
+    }
+    if !body.trim().is_empty() && !body.trim_end().ends_with(';') {
+        return Err("missing semicolon");
+    }
+
+    // Without a header, the largest index used is the number of variables.
+    let used = constraints.iter()
+                          .flat_map(|c| c.terms.iter())
+                          .chain(objective.iter().flat_map(|o| o.iter()))
+                          .map(|&(_, l)| l.abs())
+                          .max()
+                          .unwrap_or(0);
+    if used > nvar {
+        nvar = used;
+    }
+    if nvar == 0 {
+        return Err("no variables in problem");
+    }
+
+    let mut cnf = CNF::new(nvar, 0);
+    for c in constraints {
+        pseudo_boolean(&mut cnf, &c.terms, c.relation, c.rhs, encoding);
+    }
+    Ok(Opb {
+        cnf,
+        nvar,
+        objective,
+    })
+}
+
+pub fn parse_opb_file(f: File, encoding: PbEncoding) -> Result<Opb, &'static str> {
+    let mut reader = BufReader::new(f);
+    parse_opb(&mut reader, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use std::fs::File;
+    use std::io::prelude::*;
+    use std::io::SeekFrom;
+
+    use satyrs::dpll::DPLL;
+    use satyrs::encode::PbEncoding;
+    use super::parse_opb_file;
+
+    #[test]
+    fn parses_constraints_and_objective() {
+        let tmpfile = create_tempfile!("
+            * #variable= 3 #constraint= 2
+            min: +1 x1 +2 x2 ;
+            +3 x1 +2 x2 +5 x3 <= 6 ;
+            +1 x1 +1 ~x3
+              >= 1 ;
+        ");
+        let opb = parse_opb_file(tmpfile, PbEncoding::Bdd).unwrap();
+        assert_eq!(opb.nvar, 3);
+        assert_eq!(opb.objective, Some(vec![(1, 1), (2, 2)]));
+        assert!(DPLL(&opb.cnf, false).is_some());
+    }
+
+    #[test]
+    fn equality_is_unsatisfiable() {
+        let tmpfile = create_tempfile!("
+            +2 x1 +2 x2 +2 x3 = 3 ;
+        ");
+        let opb = parse_opb_file(tmpfile, PbEncoding::Adder).unwrap();
+        assert_eq!(opb.nvar, 3);
+        assert!(DPLL(&opb.cnf, false).is_none());
+    }
+
+    #[test]
+    fn model_satisfies_constraints() {
+        let tmpfile = create_tempfile!("
+            +1 x1 +1 x2 +1 x3 >= 2 ;
+            +1 x1 -1 x2 = 0 ;
+            +1 ~x3 >= 1 ;
+        ");
+        let opb = parse_opb_file(tmpfile, PbEncoding::GeneralizedTotalizer).unwrap();
+        let (solution, _) = DPLL(&opb.cnf, false).unwrap();
+        assert_eq!(&solution[..3], &[true, true, false]);
+    }
+
+    #[test]
+    fn unknown_relation() {
+        let tmpfile = create_tempfile!("
+            +1 x1 +1 x2 > 1 ;
+        ");
+        assert_eq!(parse_opb_file(tmpfile, PbEncoding::Bdd).err(), Some("unknown relation"));
+    }
+
+    #[test]
+    fn invalid_literal() {
+        let tmpfile = create_tempfile!("
+            +1 y1 +1 x2 >= 1 ;
+        ");
+        assert_eq!(parse_opb_file(tmpfile, PbEncoding::Bdd).err(), Some("invalid literal"));
+    }
+}
@@ -0,0 +1,136 @@
+//! Minimize a linear objective `sum w_i * l_i` over the models of a `CNF`.
+//!
+//! Each model found gives an upper bound; the objective is then encoded as a pseudo-Boolean
+//! constraint one below that bound, and the solver is called again on a fresh copy of the formula.
+//! The last model is optimal once the bounded formula becomes unsatisfiable.
+
+use std::time::{Duration, Instant};
+
+use satyrs::cnf::{Assignment, CNF};
+use satyrs::dpll::DPLL;
+use satyrs::encode::pb::{PbEncoding, Relation, pseudo_boolean};
+
+/// The best model found by `minimize`.
+pub struct Solution {
+    pub cost: i64,
+    /// Assignment to every variable of the solved formula, including encoder variables.
+    pub assignment: Assignment,
+    /// False if the search was cut short by the time limit.
+    pub optimal: bool,
+}
+
+/// Value of `objective` (pairs of weight and DIMACS literal) under `assn`.
+pub fn evaluate(objective: &[(i64, i32)], assn: &Assignment) -> i64 {
+    objective.iter()
+             .filter(|&&(_, l)| assn[(l.abs() - 1) as usize] == (l > 0))
+             .map(|&(w, _)| w)
+             .sum()
+}
+
+/// Minimize `objective` subject to `cnf`, whose variables must include every literal of the
+/// objective. `improved` is called with the cost and model of every improving solution as it is
+/// found. Once `timeout` has passed, no further solver calls are started and the best solution so
+/// far is returned, marked as not proven optimal. Returns `None` if `cnf` is unsatisfiable.
+pub fn minimize<F>(cnf: &CNF,
+                   objective: &[(i64, i32)],
+                   encoding: PbEncoding,
+                   timeout: Option<Duration>,
+                   mut improved: F)
+                   -> Option<Solution>
+    where F: FnMut(i64, &Assignment)
+{
+    let start = Instant::now();
+    // No model can do better than taking every negative weight.
+    let floor: i64 = objective.iter().map(|&(w, _)| if w < 0 { w } else { 0 }).sum();
+    let mut best: Option<Solution> = None;
+    loop {
+        let mut bounded = cnf.clone();
+        if let Some(ref solution) = best {
+            if solution.cost == floor {
+                break;
+            }
+            if timeout.is_some_and(|t| start.elapsed() >= t) {
+                return best.map(|s| Solution { optimal: false, ..s });
+            }
+            pseudo_boolean(&mut bounded, objective, Relation::Le, solution.cost - 1, encoding);
+        }
+        match DPLL(&bounded, false) {
+            None => break,
+            Some((assn, _)) => {
+                let cost = evaluate(objective, &assn);
+                improved(cost, &assn);
+                best = Some(Solution {
+                    cost,
+                    assignment: assn,
+                    optimal: false,
+                });
+            }
+        }
+    }
+    best.map(|s| Solution { optimal: true, ..s })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use satyrs::cnf::CNF;
+    use satyrs::encode::{CardEncoding, PbEncoding, at_least};
+    use super::*;
+
+    /// Pick at least two of 1..5, and 1 implies 2.
+    fn problem() -> CNF {
+        let mut cnf = CNF::new(5, 0);
+        at_least(&mut cnf, &[1, 2, 3, 4, 5], 2, CardEncoding::Totalizer);
+        cnf.add_clause(vec![-1, 2]);
+        cnf
+    }
+
+    #[test]
+    fn finds_optimum() {
+        let objective = [(1, 1), (4, 2), (3, 3), (5, 4), (6, 5)];
+        let mut costs = Vec::new();
+        let solution = minimize(&problem(),
+                                &objective,
+                                PbEncoding::GeneralizedTotalizer,
+                                None,
+                                |cost, _| costs.push(cost))
+                           .unwrap();
+        assert!(solution.optimal);
+        // 1 forces 2, which is still cheaper than 1 and 3 would have been
+        assert_eq!(solution.cost, 5);
+        assert_eq!(&solution.assignment[..5], &[true, true, false, false, false]);
+        // Every reported solution improves on the last
+        assert!(costs.windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(*costs.last().unwrap(), 5);
+    }
+
+    #[test]
+    fn negative_weights() {
+        let objective = [(-4, 1), (3, 2), (1, -3)];
+        let solution = minimize(&problem(), &objective, PbEncoding::Bdd, None, |_, _| {}).unwrap();
+        assert!(solution.optimal);
+        // Taking 1 (and so 2) and 3: -4 + 3 + 0
+        assert_eq!(solution.cost, -1);
+    }
+
+    #[test]
+    fn stops_at_timeout() {
+        let objective = [(1, 1), (4, 2), (3, 3), (5, 4), (6, 5)];
+        let solution = minimize(&problem(),
+                                &objective,
+                                PbEncoding::Adder,
+                                Some(Duration::from_secs(0)),
+                                |_, _| {})
+                           .unwrap();
+        assert!(!solution.optimal);
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let mut cnf = CNF::new(1, 0);
+        cnf.add_clause(vec![1]);
+        cnf.add_clause(vec![-1]);
+        assert!(minimize(&cnf, &[(1, 1)], PbEncoding::Bdd, None, |_, _| {}).is_none());
+    }
+}
@@ -0,0 +1,100 @@
+//! Parallel solving: several differently configured CDCL solvers race on the same formula, one
+//! per thread, and pass their short learned clauses to each other.
+//!
+//! Clauses are shared through an `Exchange`, which keeps only the most recent clauses. Every
+//! solver publishes the clauses it learns that are short enough, and reads what the others
+//! published whenever it restarts. The first solver to finish answers for all of them and the rest
+//! are told to stop.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use satyrs::cdcl::{Config, Heuristic, Restarts, Solver, Status};
+use satyrs::cnf::{Assignment, CNF};
+pub use satyrs::exchange::Exchange;
+
+/// `n` solver configurations that differ in heuristic, seed, restart policy, default phase and
+/// vivification, so that the solvers don't all make the same mistakes.
+pub fn diversify(n: usize) -> Vec<Config> {
+    (0..n).map(|i| {
+              let restarts = match i % 3 {
+                  0 => Restarts::Luby(100),
+                  1 => Restarts::Geometric {
+                      first: 100,
+                      factor: 1.5,
+                  },
+                  _ => Restarts::Luby(512),
+              };
+              Config {
+                  heuristic: if i % 4 == 3 { Heuristic::Jw } else { Heuristic::Vsids },
+                  decay: if i % 2 == 0 { 0.95 } else { 0.9 },
+                  restarts,
+                  random_freq: if i == 0 { 0.0 } else { 0.01 },
+                  seed: i as u64 + 1,
+                  phase: i % 2 == 1,
+                  vivify: if i % 3 == 2 { Some(Duration::from_millis(10)) } else { None },
+              }
+          })
+          .collect()
+}
+
+/// Solve `cnf` with one thread per configuration, sharing learned clauses of at most `max_len`
+/// literals. Returns a model, or `None` if the formula is unsatisfiable. Panics if there are no
+/// configurations, as nothing would be left to answer.
+pub fn solve(cnf: &CNF, configs: &[Config], max_len: usize, verbose: bool) -> Option<Assignment> {
+    assert!(!configs.is_empty(), "parallel::solve needs at least one configuration");
+    let stop = Arc::new(AtomicBool::new(false));
+    let exchange = Arc::new(Exchange::new(10_000, max_len));
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        for (id, config) in configs.iter().enumerate() {
+            let tx = tx.clone();
+            let stop = stop.clone();
+            let exchange = exchange.clone();
+            scope.spawn(move || {
+                let mut solver = Solver::new(cnf, config.clone());
+                solver.set_stop(stop);
+                solver.share(exchange, id);
+                let answer = match solver.solve() {
+                    Status::Satisfiable => Some(solver.model().clone()),
+                    Status::Unsatisfiable => None,
+                    // Another solver has already answered
+                    Status::Unknown => return,
+                };
+                if verbose {
+                    println!("Solver {} answered after {:?}", id, solver.stats);
+                }
+                let _ = tx.send(answer);
+            });
+        }
+        drop(tx);
+        let answer = rx.recv().expect("every solver stopped without an answer");
+        stop.store(true, Ordering::Relaxed);
+        answer
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use super::*;
+
+    #[test]
+    fn solves_with_several_threads() {
+        let cnf = parse_file("tests/uf250-01.cnf");
+        let model = solve(&cnf, &diversify(4), 8, false).unwrap();
+        assert!(satisfies(&cnf, &model));
+
+        let cnf = parse_file("tests/dubois29_unsat.cnf");
+        assert_eq!(solve(&cnf, &diversify(4), 8, false), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one configuration")]
+    fn needs_a_configuration() {
+        let cnf = CNF::new(1, 0);
+        let _ = solve(&cnf, &diversify(0), 8, false);
+    }
+}
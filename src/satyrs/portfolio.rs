@@ -0,0 +1,127 @@
+//! Portfolio solving: race stochastic local search against complete search on the same formula.
+//!
+//! Local search is quick on random satisfiable instances but can never prove unsatisfiability,
+//! while CDCL is the other way around. Both run on their own thread, and whichever answers first
+//! wins; local search only answers if it finds a model. While it runs, its best assignment so far
+//! can be taken as CDCL's saved phases at every restart.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use satyrs::cdcl::{self, Solver, Status};
+use satyrs::cnf::{Assignment, CNF};
+use satyrs::sls::{self, Strategy};
+
+pub struct Config {
+    pub strategy: Strategy,
+    /// Flip limit for local search. Once it runs out, only DPLL keeps going.
+    pub max_flips: u64,
+    pub seed: u64,
+    /// Whether CDCL should take the best local search assignment as its phases on restarts.
+    pub seed_phases: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            strategy: Strategy::probsat(),
+            max_flips: u64::MAX,
+            seed: 1,
+            seed_phases: true,
+        }
+    }
+}
+
+/// The engine that settled the formula.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    LocalSearch,
+    Complete,
+}
+
+/// Decide `cnf`, returning a model if there is one, along with which engine answered.
+pub fn solve(cnf: &CNF, config: &Config, verbose: bool) -> (Option<Assignment>, Engine) {
+    let stop = Arc::new(AtomicBool::new(false));
+    // Empty until local search reports, so that CDCL keeps its own phases until then
+    let phases = Arc::new(Mutex::new(Vec::new()));
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        let sls_tx = tx.clone();
+        let (sls_stop, sls_phases) = (stop.clone(), phases.clone());
+        scope.spawn(move || {
+            let model = sls::solve_with(cnf,
+                                        config.strategy,
+                                        config.max_flips,
+                                        config.seed,
+                                        &sls_stop,
+                                        |unsat, assn| {
+                                            if verbose {
+                                                println!("Local search: {} falsified", unsat);
+                                            }
+                                            if config.seed_phases {
+                                                sls_phases.lock().unwrap().clone_from(assn);
+                                            }
+                                        });
+            // Giving up says nothing either way, so leave the answer to CDCL
+            if model.is_some() {
+                let _ = sls_tx.send((model, Engine::LocalSearch));
+            }
+        });
+
+        let (cdcl_stop, cdcl_phases) = (stop.clone(), phases.clone());
+        scope.spawn(move || {
+            let mut solver = Solver::new(cnf, cdcl::Config::default());
+            solver.set_stop(cdcl_stop);
+            if config.seed_phases {
+                solver.follow_phases(cdcl_phases);
+            }
+            let model = match solver.solve() {
+                Status::Satisfiable => Some(solver.model().clone()),
+                Status::Unsatisfiable => None,
+                // Local search has already answered
+                Status::Unknown => return,
+            };
+            let _ = tx.send((model, Engine::Complete));
+        });
+
+        let answer = rx.recv().unwrap();
+        stop.store(true, Ordering::Relaxed);
+        if verbose {
+            println!("Answered by {:?}", answer.1);
+        }
+        answer
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use super::*;
+
+    #[test]
+    fn solves_random_3sat() {
+        let cnf = parse_file("tests/uf250-01.cnf");
+        let (model, _) = solve(&cnf, &Config::default(), false);
+        assert!(satisfies(&cnf, &model.unwrap()));
+    }
+
+    #[test]
+    fn complete_search_proves_unsatisfiable() {
+        // Two pigeons, one hole
+        let mut cnf = CNF::new(2, 0);
+        cnf.add_clause(vec![1]);
+        cnf.add_clause(vec![2]);
+        cnf.add_clause(vec![-1, -2]);
+        let config = Config { max_flips: 10_000, ..Config::default() };
+        assert_eq!(solve(&cnf, &config, false), (None, Engine::Complete));
+    }
+
+    #[test]
+    fn without_phase_seeding() {
+        let cnf = parse_file("tests/medium.cnf");
+        let config = Config { seed_phases: false, ..Config::default() };
+        let (model, _) = solve(&cnf, &config, false);
+        assert!(satisfies(&cnf, &model.unwrap()));
+    }
+}
@@ -0,0 +1,114 @@
+//! Blocked clause elimination.
+//!
+//! A clause C is blocked on one of its literals `l` if every resolvent of C on `l` is a tautology,
+//! that is, every clause containing `-l` also contains the negation of another literal of C. Such
+//! a clause can be removed without changing satisfiability: a model that falsifies it satisfies it
+//! again by flipping `l`, and no clause containing `-l` can be falsified by that. Encodings of
+//! circuits are full of them. Pure literals are the special case where `-l` occurs nowhere.
+//!
+//! Removing a clause only makes it easier for others to be blocked, so this also works on the
+//! formulas of a search in progress, as long as the removed clauses are repaired in the model it
+//! finds; `DPLL_with` can run it every few levels.
+
+use std::collections::BTreeSet;
+
+use satyrs::cnf::CNF;
+use super::{Reconstruction, Stats};
+
+/// Is clause `id`, which contains `lit`, blocked on `lit`?
+pub fn blocked(cnf: &CNF, id: i32, lit: i32) -> bool {
+    let clause = &cnf.clauses[&id];
+    cnf.occurrences.get(&(lit ^ 1)).is_none_or(|occ| {
+        occ.iter().all(|d| {
+            cnf.clauses[d].iter().any(|l| *l != lit ^ 1 && clause.contains(&(l ^ 1)))
+        })
+    })
+}
+
+/// Remove blocked clauses until there are none left, recording each with the literal it is
+/// blocked on.
+pub fn eliminate_blocked(cnf: &mut CNF, stack: &mut Reconstruction) -> Stats {
+    let mut stats = Stats::default();
+    // Ordered, so that clauses go in the same order every time
+    let mut queue: BTreeSet<i32> = cnf.clauses.keys().cloned().collect();
+    while let Some(id) = queue.iter().next().cloned() {
+        queue.remove(&id);
+        let mut lits: Vec<i32> = match cnf.clauses.get(&id) {
+            Some(clause) => clause.iter().cloned().collect(),
+            None => continue,
+        };
+        lits.sort();
+        if let Some(lit) = lits.iter().cloned().find(|l| blocked(cnf, id, *l)) {
+            cnf.remove_clause(id);
+            stack.push(lit, lits.clone());
+            stats.clauses_removed += 1;
+            // Clauses resolving with this one on another literal may be blocked now
+            for l in lits {
+                if let Some(occ) = cnf.occurrences.get(&(l ^ 1)) {
+                    queue.extend(occ.iter().cloned());
+                }
+            }
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::dpll::{DPLL_with, Options};
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use satyrs::preprocess::tests::check_model;
+    use satyrs::preprocess::{Config, Technique, preprocess};
+    use super::*;
+
+    #[test]
+    fn removes_blocked_clauses() {
+        // (1 2) is blocked on 1: its only resolvent, with (-1 -2), is a tautology
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![-1, -2]);
+        cnf.add_clause(vec![2, 3]);
+        cnf.add_clause(vec![-2, -3]);
+        assert!(blocked(&cnf, 0, 1 << 1));
+
+        let mut simplified = cnf.clone();
+        let mut stack = Reconstruction::new();
+        let stats = eliminate_blocked(&mut simplified, &mut stack);
+        assert_eq!(stats.clauses_removed, 4);
+        assert!(simplified.clauses.is_empty());
+        let mut model = vec![true, true, true];
+        stack.extend(&mut model);
+        assert!(satisfies(&cnf, &model));
+    }
+
+    #[test]
+    fn keeps_models() {
+        let config = Config {
+            techniques: vec![Technique::BlockedClauses],
+            rounds: 1,
+        };
+        for name in ["tests/medium.cnf", "tests/med_64_254.cnf"].iter() {
+            let cnf = parse_file(name);
+            check_model(&cnf, &preprocess(&cnf, &config).unwrap());
+        }
+    }
+
+    #[test]
+    fn inprocessing_in_dpll() {
+        let options = Options {
+            inprocess: Some(2),
+            ..Options::default()
+        };
+        for name in ["tests/medium.cnf", "tests/med_64_254.cnf", "tests/cascade.cnf"].iter() {
+            let cnf = parse_file(name);
+            let (model, _) = DPLL_with(&cnf, false, &options).unwrap();
+            assert!(satisfies(&cnf, &model));
+        }
+        let mut cnf = CNF::new(2, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![1, -2]);
+        cnf.add_clause(vec![-1, -2]);
+        assert!(DPLL_with(&cnf, false, &options).is_none());
+    }
+}
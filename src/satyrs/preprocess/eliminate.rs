@@ -0,0 +1,156 @@
+//! Bounded variable elimination, as in SatELite.
+//!
+//! Replacing every clause containing `x` or `-x` by all their non-tautological resolvents on `x`
+//! gives a formula without `x` that is satisfiable exactly when the original is. It is only done
+//! when there are no more resolvents than clauses they replace, so the formula never grows.
+//!
+//! The replaced clauses go on the reconstruction stack with their literal of `x` as witness. A
+//! model of the resolvents falsifies clauses of at most one polarity of `x`, as otherwise it would
+//! falsify their resolvent, so setting `x` to fix them leaves the others satisfied.
+
+use std::collections::HashSet;
+
+use satyrs::cnf::CNF;
+use super::subsume::subsumed;
+use super::{Reconstruction, Stats};
+
+/// The resolvent of `c` and `d` on the variable of `lit`, which `c` contains and `d` contains
+/// negated, or `None` if it is a tautology.
+fn resolve(c: &HashSet<i32>, d: &HashSet<i32>, lit: i32) -> Option<HashSet<i32>> {
+    let mut resolvent: HashSet<i32> = c.iter().cloned().filter(|l| *l != lit).collect();
+    for &l in d {
+        if l == lit ^ 1 {
+            continue;
+        }
+        if resolvent.contains(&(l ^ 1)) {
+            return None;
+        }
+        resolvent.insert(l);
+    }
+    Some(resolvent)
+}
+
+fn ids(cnf: &CNF, lit: i32) -> Vec<i32> {
+    let mut ids: Vec<i32> = cnf.occurrences
+                               .get(&lit)
+                               .map_or(Vec::new(), |o| o.iter().cloned().collect());
+    ids.sort();
+    ids
+}
+
+/// The resolvents of eliminating `var`, or `None` if there would be more of them than clauses
+/// containing `var`.
+fn resolvents(cnf: &CNF, var: i32) -> Option<Vec<HashSet<i32>>> {
+    let (pos, neg) = (ids(cnf, var << 1), ids(cnf, var << 1 | 1));
+    let bound = pos.len() + neg.len();
+    let mut result = Vec::new();
+    for p in &pos {
+        for n in &neg {
+            if let Some(resolvent) = resolve(&cnf.clauses[p], &cnf.clauses[n], var << 1) {
+                result.push(resolvent);
+                if result.len() > bound {
+                    return None;
+                }
+            }
+        }
+    }
+    Some(result)
+}
+
+/// Eliminate every variable whose elimination doesn't add clauses, cheapest first. Returns `None`
+/// if an empty resolvent showed the formula unsatisfiable.
+pub fn eliminate(cnf: &mut CNF, stack: &mut Reconstruction) -> Option<Stats> {
+    let mut stats = Stats::default();
+    let count = |cnf: &CNF, lit: i32| cnf.occurrences.get(&lit).map_or(0, |o| o.len());
+    let mut vars: Vec<i32> = cnf.occurrences.keys().map(|l| l >> 1).collect();
+    vars.sort_by_key(|v| (count(cnf, v << 1) * count(cnf, v << 1 | 1), *v));
+    vars.dedup();
+
+    for var in vars {
+        let (pos, neg) = (var << 1, var << 1 | 1);
+        // Clauses with both `x` and `-x` would leave `x` in their resolvents
+        if (!cnf.occurrences.contains_key(&pos) && !cnf.occurrences.contains_key(&neg))
+           || ids(cnf, pos).iter().any(|id| cnf.clauses[id].contains(&neg)) {
+            continue;
+        }
+        let resolvents = match resolvents(cnf, var) {
+            Some(resolvents) => resolvents,
+            None => continue,
+        };
+        for &lit in [pos, neg].iter() {
+            for id in ids(cnf, lit) {
+                let mut clause: Vec<i32> = cnf.remove_clause(id).unwrap().into_iter().collect();
+                clause.sort();
+                stack.push(lit, clause);
+                stats.clauses_removed += 1;
+            }
+        }
+        for resolvent in resolvents {
+            if resolvent.is_empty() {
+                return None;
+            }
+            if !subsumed(cnf, &resolvent) {
+                cnf.insert_clause(resolvent);
+                stats.clauses_added += 1;
+            }
+        }
+    }
+    Some(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::test_util::parse_file;
+    use satyrs::preprocess::tests::check_model;
+    use satyrs::preprocess::{Config, Technique, preprocess, run};
+    use super::*;
+
+    #[test]
+    fn eliminates_and_reconstructs() {
+        // 2 is defined as 1 AND 3, and occurs nowhere else
+        let mut cnf = CNF::new(4, 0);
+        cnf.add_clause(vec![-2, 1]);
+        cnf.add_clause(vec![-2, 3]);
+        cnf.add_clause(vec![2, -1, -3]);
+        cnf.add_clause(vec![1, 4]);
+        cnf.add_clause(vec![3, -4]);
+        cnf.add_clause(vec![-1, -3, 4]);
+        let mut simplified = cnf.clone();
+        let stats = run(Technique::Elimination, &mut simplified, &mut Reconstruction::new());
+        let stats = stats.unwrap();
+        assert!(stats.variables_removed >= 1);
+        assert!(stats.clauses_added <= stats.clauses_removed);
+        assert!(!simplified.occurrences.contains_key(&(2 << 1)));
+
+        let config = Config {
+            techniques: vec![Technique::Elimination],
+            rounds: 1,
+        };
+        check_model(&cnf, &preprocess(&cnf, &config).unwrap());
+    }
+
+    #[test]
+    fn empty_resolvent() {
+        let mut cnf = CNF::new(1, 0);
+        cnf.add_clause(vec![1]);
+        cnf.add_clause(vec![-1]);
+        assert!(eliminate(&mut cnf, &mut Reconstruction::new()).is_none());
+    }
+
+    #[test]
+    fn shrinks_files() {
+        let config = Config {
+            techniques: vec![Technique::Units, Technique::Elimination],
+            rounds: 2,
+        };
+        let cnf = parse_file("tests/medium.cnf");
+        let result = preprocess(&cnf, &config).unwrap();
+        assert!(result.cnf.clauses.len() < cnf.clauses.len());
+        check_model(&cnf, &result);
+
+        let cnf = parse_file("tests/long_1318_3668.cnf");
+        let result = preprocess(&cnf, &config).unwrap();
+        assert!(result.cnf.clauses.len() * 3 < cnf.clauses.len() * 2);
+        assert!(result.stats[1].1.variables_removed > 0);
+    }
+}
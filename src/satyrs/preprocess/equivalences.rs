@@ -0,0 +1,186 @@
+//! Equivalent literal substitution.
+//!
+//! Every binary clause `(a b)` is a pair of implications, `-a -> b` and `-b -> a`. Literals in the
+//! same strongly connected component of the graph of these implications imply each other, so they
+//! are all equivalent and can be replaced by one representative. If a component contains both `x`
+//! and `-x`, then `x` is equivalent to its own negation, and the formula is unsatisfiable.
+//!
+//! The representative of a component is its smallest literal, so the component of the negations
+//! gets the negated representative. A substituted variable `x` with representative `r` gets its
+//! value back from the reconstruction clauses `(-x r)` and `(x -r)`.
+
+use satyrs::cnf::CNF;
+use super::{Reconstruction, Stats};
+
+/// Components of the binary implication graph of `cnf`, with Tarjan's algorithm. Iterative, since
+/// the chains of implications in encoded circuits are long.
+fn components(cnf: &CNF) -> Vec<Vec<usize>> {
+    let size = 2 * cnf.nvar as usize + 2;
+    let mut edges = vec![Vec::new(); size];
+    let mut ids: Vec<&i32> = cnf.clauses.keys().collect();
+    ids.sort();
+    for id in ids {
+        let clause = &cnf.clauses[id];
+        if clause.len() == 2 {
+            let mut lits = clause.iter().map(|l| *l as usize);
+            let (a, b) = (lits.next().unwrap(), lits.next().unwrap());
+            edges[a ^ 1].push(b);
+            edges[b ^ 1].push(a);
+        }
+    }
+
+    let mut index = vec![usize::MAX; size];
+    let mut lowlink = vec![0; size];
+    let mut on_stack = vec![false; size];
+    let mut stack = Vec::new();
+    let mut next = 0;
+    let mut components = Vec::new();
+    for root in 2..size {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        // Literals being visited, with the next edge to follow from each
+        let mut visiting = vec![(root, 0)];
+        index[root] = next;
+        lowlink[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some(&mut (lit, ref mut edge)) = visiting.last_mut() {
+            if let Some(&succ) = edges[lit].get(*edge) {
+                *edge += 1;
+                if index[succ] == usize::MAX {
+                    index[succ] = next;
+                    lowlink[succ] = next;
+                    next += 1;
+                    stack.push(succ);
+                    on_stack[succ] = true;
+                    visiting.push((succ, 0));
+                } else if on_stack[succ] {
+                    lowlink[lit] = lowlink[lit].min(index[succ]);
+                }
+                continue;
+            }
+            visiting.pop();
+            if let Some(&(parent, _)) = visiting.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[lit]);
+            }
+            if lowlink[lit] == index[lit] {
+                let mut component = Vec::new();
+                loop {
+                    let l = stack.pop().unwrap();
+                    on_stack[l] = false;
+                    component.push(l);
+                    if l == lit {
+                        break;
+                    }
+                }
+                if component.len() > 1 {
+                    components.push(component);
+                }
+            }
+        }
+    }
+    components
+}
+
+/// Replace every literal by the representative of its component. Returns `None` if some variable
+/// is equivalent to its own negation.
+pub fn substitute(cnf: &mut CNF, stack: &mut Reconstruction) -> Option<Stats> {
+    let mut stats = Stats::default();
+    let mut representative: Vec<usize> = (0..2 * cnf.nvar as usize + 2).collect();
+    for component in components(cnf) {
+        let r = *component.iter().min().unwrap();
+        for &lit in &component {
+            if component.contains(&(lit ^ 1)) {
+                return None;
+            }
+            representative[lit] = r;
+        }
+    }
+
+    for var in 1..=cnf.nvar {
+        let (x, r) = (var << 1, representative[(var << 1) as usize] as i32);
+        if r == x {
+            continue;
+        }
+        for &lit in [x, x ^ 1].iter() {
+            let substitute = if lit == x { r } else { r ^ 1 };
+            let mut ids: Vec<i32> = match cnf.occurrences.get(&lit) {
+                Some(occ) => occ.iter().cloned().collect(),
+                None => continue,
+            };
+            ids.sort();
+            for id in ids {
+                let mut clause = cnf.remove_clause(id).unwrap();
+                clause.remove(&lit);
+                if clause.contains(&(substitute ^ 1)) {
+                    stats.clauses_removed += 1;
+                    continue;
+                }
+                if !clause.insert(substitute) {
+                    stats.clauses_strengthened += 1;
+                }
+                cnf.insert_clause(clause);
+            }
+        }
+        stack.push(x ^ 1, vec![x ^ 1, r]);
+        stack.push(x, vec![x, r ^ 1]);
+    }
+    Some(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use satyrs::preprocess::tests::check_model;
+    use satyrs::preprocess::{Config, Technique, preprocess};
+    use super::*;
+
+    #[test]
+    fn substitutes_cycles() {
+        // 1 -> 2 -> -3 -> 1
+        let mut cnf = CNF::new(4, 0);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![-2, -3]);
+        cnf.add_clause(vec![3, 1]);
+        cnf.add_clause(vec![2, 3, 4]);
+        cnf.add_clause(vec![-4, -1]);
+        let mut simplified = cnf.clone();
+        let mut stack = Reconstruction::new();
+        let stats = substitute(&mut simplified, &mut stack).unwrap();
+        // Every clause but (-4 -1) becomes a tautology, (2 3 4) by way of (1 -1 4)
+        assert_eq!(stats.clauses_removed, 4);
+        assert_eq!(simplified.clauses.len(), 1);
+        let expected: HashSet<i32> = [4 << 1 | 1, 1 << 1 | 1].iter().cloned().collect();
+        assert!(simplified.clauses.values().all(|c| *c == expected));
+
+        let mut model = vec![false, true, true, false];
+        stack.extend(&mut model);
+        assert!(satisfies(&cnf, &model));
+    }
+
+    #[test]
+    fn contradictory_component() {
+        // 1 -> 2 -> -1 -> 3 -> 1
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![-2, -1]);
+        cnf.add_clause(vec![1, 3]);
+        cnf.add_clause(vec![-3, 1]);
+        assert!(substitute(&mut cnf, &mut Reconstruction::new()).is_none());
+    }
+
+    #[test]
+    fn keeps_models() {
+        let config = Config {
+            techniques: vec![Technique::Equivalences],
+            rounds: 2,
+        };
+        for name in ["tests/medium.cnf", "tests/med_64_254.cnf"].iter() {
+            let cnf = parse_file(name);
+            check_model(&cnf, &preprocess(&cnf, &config).unwrap());
+        }
+    }
+}
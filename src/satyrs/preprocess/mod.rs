@@ -0,0 +1,278 @@
+//! Preprocessing: simplify a `CNF` once, before search, instead of at every node of `_dpll`.
+//!
+//! A pipeline runs the configured techniques over a copy of the formula, in order, for a number of
+//! rounds or until nothing changes. Techniques that remove clauses which are not implied by the
+//! rest (like pure literals) may make the simplified formula have models that don't satisfy the
+//! original. They record what they removed on a `Reconstruction` stack, which repairs such a model
+//! afterwards.
+//!
+//! Variables keep their numbers in the simplified formula; removed variables just stop occurring.
+//! Like `CNF`, techniques work on internal literals.
+
+use satyrs::cnf::{Assignment, CNF};
+
+pub mod subsume;
+pub mod eliminate;
+pub mod blocked;
+pub mod equivalences;
+pub mod probe;
+
+/// Clauses removed during preprocessing, each with a witness literal, in the order they were
+/// removed. Going back through the stack, every clause the model falsifies gets its witness set
+/// true. Flipping a witness can only falsify clauses removed before it, which come later.
+#[derive(Debug, Clone, Default)]
+pub struct Reconstruction {
+    stack: Vec<(i32, Vec<i32>)>,
+}
+
+impl Reconstruction {
+    pub fn new() -> Reconstruction {
+        Reconstruction::default()
+    }
+
+    /// Record that `clause` was removed, to be repaired by setting `witness`.
+    pub fn push(&mut self, witness: i32, clause: Vec<i32>) {
+        self.stack.push((witness, clause));
+    }
+
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Turn a model of the simplified formula into a model of the original, in place.
+    pub fn extend(&self, model: &mut Assignment) {
+        let value = |model: &Assignment, l: i32| model[((l >> 1) - 1) as usize] == (l & 1 == 0);
+        for &(witness, ref clause) in self.stack.iter().rev() {
+            if !clause.iter().any(|l| value(model, *l)) {
+                model[((witness >> 1) - 1) as usize] = witness & 1 == 0;
+            }
+        }
+    }
+}
+
+/// A simplification that can take part in the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Technique {
+    /// Assign the literals of unit clauses and propagate them.
+    Units,
+    /// Assign literals whose negation occurs nowhere.
+    PureLiterals,
+    /// Remove subsumed clauses and strengthen clauses by self-subsuming resolution.
+    Subsumption,
+    /// Eliminate variables by resolution where that doesn't add clauses.
+    Elimination,
+    /// Remove clauses that are blocked on one of their literals.
+    BlockedClauses,
+    /// Replace literals that imply each other through binary clauses by one of them.
+    Equivalences,
+    /// Assign the negations of failed literals, and literals both values of a variable imply.
+    /// Optionally add hyper-binary resolvents found on the way.
+    Probing { hyper_binary: bool },
+}
+
+/// What a technique did to the formula.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub clauses_removed: usize,
+    /// Clauses that lost literals.
+    pub clauses_strengthened: usize,
+    pub clauses_added: usize,
+    /// Variables that no longer occur because of the technique.
+    pub variables_removed: usize,
+}
+
+impl Stats {
+    fn changed(&self) -> bool {
+        *self != Stats::default()
+    }
+
+    fn add(&mut self, other: &Stats) {
+        self.clauses_removed += other.clauses_removed;
+        self.clauses_strengthened += other.clauses_strengthened;
+        self.clauses_added += other.clauses_added;
+        self.variables_removed += other.variables_removed;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Techniques to run, in this order, every round.
+    pub techniques: Vec<Technique>,
+    /// Stop after this many rounds even if the formula is still shrinking.
+    pub rounds: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            techniques: vec![Technique::Units,
+                             Technique::PureLiterals,
+                             Technique::Probing { hyper_binary: false },
+                             Technique::Equivalences,
+                             Technique::Subsumption,
+                             Technique::Elimination,
+                             Technique::BlockedClauses],
+            rounds: 5,
+        }
+    }
+}
+
+/// The result of preprocessing.
+pub struct Preprocessed {
+    pub cnf: CNF,
+    pub reconstruction: Reconstruction,
+    /// Totals for every technique that ran.
+    pub stats: Vec<(Technique, Stats)>,
+}
+
+/// Run `technique` once. Returns `None` if it found the formula unsatisfiable.
+pub fn run(technique: Technique, cnf: &mut CNF, stack: &mut Reconstruction) -> Option<Stats> {
+    let before = occurring(cnf);
+    let mut stats = match technique {
+        Technique::Units => units(cnf, stack)?,
+        Technique::PureLiterals => pure_literals(cnf, stack),
+        Technique::Subsumption => subsume::subsume(cnf)?,
+        Technique::Elimination => eliminate::eliminate(cnf, stack)?,
+        Technique::BlockedClauses => blocked::eliminate_blocked(cnf, stack),
+        Technique::Equivalences => equivalences::substitute(cnf, stack)?,
+        Technique::Probing { hyper_binary } => probe::probe(cnf, stack, hyper_binary)?,
+    };
+    stats.variables_removed = before - occurring(cnf);
+    Some(stats)
+}
+
+/// Simplify `cnf` with the techniques of `config`. Returns `None` if preprocessing alone showed
+/// the formula unsatisfiable.
+pub fn preprocess(cnf: &CNF, config: &Config) -> Option<Preprocessed> {
+    let mut result = Preprocessed {
+        cnf: cnf.clone(),
+        reconstruction: Reconstruction::new(),
+        stats: config.techniques.iter().map(|t| (*t, Stats::default())).collect(),
+    };
+    if result.cnf.clauses.values().any(|c| c.is_empty()) {
+        return None;
+    }
+    for _ in 0..config.rounds {
+        let mut changed = false;
+        for i in 0..config.techniques.len() {
+            let stats = run(config.techniques[i], &mut result.cnf, &mut result.reconstruction)?;
+            changed |= stats.changed();
+            result.stats[i].1.add(&stats);
+        }
+        if !changed {
+            break;
+        }
+    }
+    Some(result)
+}
+
+/// Variables occurring in `cnf`.
+fn occurring(cnf: &CNF) -> usize {
+    let mut vars: Vec<i32> = cnf.occurrences.keys().map(|l| l >> 1).collect();
+    vars.sort();
+    vars.dedup();
+    vars.len()
+}
+
+/// Assign `lit`, recording it for reconstruction. Returns `None` on a conflict.
+fn assign(cnf: &mut CNF, lit: i32, stack: &mut Reconstruction, stats: &mut Stats) -> Option<()> {
+    stack.push(lit, vec![lit]);
+    stats.clauses_removed += cnf.occurrences.get(&lit).map_or(0, |o| o.len());
+    stats.clauses_strengthened += cnf.occurrences.get(&(lit ^ 1)).map_or(0, |o| o.len());
+    cnf.propagate(lit);
+    if cnf.clauses.values().any(|c| c.is_empty()) {
+        return None;
+    }
+    Some(())
+}
+
+fn units(cnf: &mut CNF, stack: &mut Reconstruction) -> Option<Stats> {
+    let mut stats = Stats::default();
+    // `CNF::propagate` doesn't always keep `units` up to date, so look at the clauses themselves
+    while let Some(lit) = cnf.clauses.values().find(|c| c.len() == 1).map(|c| zeroth!(c)) {
+        assign(cnf, lit, stack, &mut stats)?;
+    }
+    cnf.units.clear();
+    Some(stats)
+}
+
+fn pure_literals(cnf: &mut CNF, stack: &mut Reconstruction) -> Stats {
+    let mut stats = Stats::default();
+    loop {
+        let mut pures: Vec<i32> = cnf.occurrences
+                                     .keys()
+                                     .filter(|l| !cnf.occurrences.contains_key(&(*l ^ 1)))
+                                     .cloned()
+                                     .collect();
+        if pures.is_empty() {
+            break;
+        }
+        pures.sort();
+        for lit in pures {
+            // Only clauses containing `lit` go away, so this can't fail
+            let _ = assign(cnf, lit, stack, &mut stats);
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+pub mod tests {
+    use satyrs::cnf::CNF;
+    use satyrs::dpll::DPLL;
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use super::*;
+
+    /// Solve the simplified formula and check the reconstructed model against the original.
+    pub fn check_model(original: &CNF, preprocessed: &Preprocessed) {
+        let (mut model, _) = DPLL(&preprocessed.cnf, false).expect("simplified formula is UNSAT");
+        preprocessed.reconstruction.extend(&mut model);
+        assert!(satisfies(original, &model));
+    }
+
+    #[test]
+    fn units_cascade() {
+        let cnf = parse_file("tests/cascade.cnf");
+        let config = Config {
+            techniques: vec![Technique::Units],
+            rounds: 1,
+        };
+        let result = preprocess(&cnf, &config).unwrap();
+        assert!(result.cnf.clauses.is_empty());
+        assert_eq!(result.stats[0].1.variables_removed, 4);
+        check_model(&cnf, &result);
+    }
+
+    #[test]
+    fn pure_literals_need_reconstruction() {
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![1, -2, 3]);
+        cnf.add_clause(vec![-3, 2]);
+        let result = preprocess(&cnf, &Config::default()).unwrap();
+        assert!(result.cnf.clauses.is_empty());
+        let mut model = vec![false, false, false];
+        result.reconstruction.extend(&mut model);
+        assert!(satisfies(&cnf, &model));
+    }
+
+    #[test]
+    fn refutes_contradictory_units() {
+        let mut cnf = CNF::new(2, 0);
+        cnf.add_clause(vec![1]);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![-2]);
+        assert!(preprocess(&cnf, &Config::default()).is_none());
+    }
+
+    #[test]
+    fn keeps_models() {
+        let cnf = parse_file("tests/medium.cnf");
+        let result = preprocess(&cnf, &Config::default()).unwrap();
+        check_model(&cnf, &result);
+    }
+}
@@ -0,0 +1,173 @@
+//! Failed literal probing.
+//!
+//! Probing a literal assigns it on a lookahead trail, propagates, and takes it back. If that ends
+//! in a conflict the literal is failed, and its negation holds. If both literals of a variable
+//! imply some literal, it holds too. Either way the new units are implied by the formula, so they
+//! need no reconstruction beyond their own assignment.
+//!
+//! Every literal `q` a probe on `l` propagates also gives the implied binary clause `(-l q)`. Those
+//! `q` that binary clauses alone don't reach from `l` needed a longer clause, and adding their
+//! binary clauses (hyper-binary resolvents) speeds up later propagation and lets equivalent
+//! literal substitution find more.
+
+use std::collections::HashSet;
+
+use satyrs::cnf::CNF;
+use satyrs::lookahead::Lookahead;
+use super::subsume::subsumed;
+use super::{Reconstruction, Stats, assign};
+
+/// Literals binary clauses of `cnf` reach from `lit`, including `lit`.
+fn binary_closure(cnf: &CNF, lit: i32) -> HashSet<i32> {
+    let mut reached: HashSet<i32> = HashSet::new();
+    let mut queue = vec![lit];
+    reached.insert(lit);
+    while let Some(l) = queue.pop() {
+        let occ = match cnf.occurrences.get(&(l ^ 1)) {
+            Some(occ) => occ,
+            None => continue,
+        };
+        for id in occ {
+            let clause = &cnf.clauses[id];
+            if clause.len() == 2 {
+                let other = *clause.iter().find(|q| **q != l ^ 1).unwrap();
+                if reached.insert(other) {
+                    queue.push(other);
+                }
+            }
+        }
+    }
+    reached
+}
+
+/// Probe both literals of every variable. Returns `None` if both of some variable fail.
+pub fn probe(cnf: &mut CNF, stack: &mut Reconstruction, hyper_binary: bool) -> Option<Stats> {
+    let mut stats = Stats::default();
+    let mut lookahead = Lookahead::new(cnf);
+    if !lookahead.consistent() {
+        return None;
+    }
+    let mut vars: Vec<i32> = cnf.occurrences.keys().map(|l| l >> 1).collect();
+    vars.sort();
+    vars.dedup();
+    let mut binaries: Vec<HashSet<i32>> = Vec::new();
+
+    for var in vars {
+        if lookahead.value((var << 1) as usize).is_some() {
+            continue;
+        }
+        let mut implied: Vec<Option<Vec<i32>>> = Vec::new();
+        for &lit in [var << 1, var << 1 | 1].iter() {
+            let mark = lookahead.mark();
+            if lookahead.assign(lit as usize) {
+                let lits: Vec<i32> = lookahead.assigned_since(mark)
+                                              .iter()
+                                              .map(|l| *l as i32)
+                                              .collect();
+                implied.push(Some(lits));
+            } else {
+                implied.push(None);
+            }
+            lookahead.undo(mark);
+        }
+        let units: Vec<i32> = match (implied[0].as_ref(), implied[1].as_ref()) {
+            (None, None) => return None,
+            (None, Some(_)) => vec![var << 1 | 1],
+            (Some(_), None) => vec![var << 1],
+            (Some(pos), Some(neg)) => {
+                if hyper_binary {
+                    for &(lit, lits) in [(var << 1, pos), (var << 1 | 1, neg)].iter() {
+                        let reached = binary_closure(cnf, lit);
+                        for q in lits.iter().filter(|q| !reached.contains(q)) {
+                            binaries.push([lit ^ 1, *q].iter().cloned().collect());
+                        }
+                    }
+                }
+                pos.iter().cloned().filter(|l| neg.contains(l)).collect()
+            }
+        };
+        for unit in units {
+            if !lookahead.assign(unit as usize) {
+                return None;
+            }
+        }
+    }
+
+    for binary in binaries {
+        if !subsumed(cnf, &binary) {
+            cnf.insert_clause(binary);
+            stats.clauses_added += 1;
+        }
+    }
+    // The trail holds the units of the formula, the forced literals, and what they propagated
+    let forced: Vec<i32> = lookahead.assigned_since(0).iter().map(|l| *l as i32).collect();
+    for lit in forced {
+        if cnf.occurrences.contains_key(&lit) || cnf.occurrences.contains_key(&(lit ^ 1)) {
+            assign(cnf, lit, stack, &mut stats)?;
+        }
+    }
+    Some(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::test_util::parse_file;
+    use satyrs::preprocess::tests::check_model;
+    use satyrs::preprocess::{Config, Technique, preprocess};
+    use super::*;
+
+    #[test]
+    fn failed_and_common_literals() {
+        let mut cnf = CNF::new(4, 0);
+        // 1 fails: it implies 2 and -2
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![-1, -2]);
+        // 3 and -3 both imply 4
+        cnf.add_clause(vec![-3, 4]);
+        cnf.add_clause(vec![3, 4, 1]);
+        cnf.add_clause(vec![2, 3, -4]);
+        let mut simplified = cnf.clone();
+        let mut stack = Reconstruction::new();
+        probe(&mut simplified, &mut stack, false).unwrap();
+        let mut model = vec![true; 4];
+        stack.extend(&mut model);
+        assert!(!model[0]);
+        assert!(model[3]);
+        assert!(simplified.clauses.values().all(|c| !c.contains(&(1 << 1))));
+    }
+
+    #[test]
+    fn hyper_binary_resolvents() {
+        // 1 implies 2 and 3, which together imply 4
+        let mut cnf = CNF::new(4, 0);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![-1, 3]);
+        cnf.add_clause(vec![-2, -3, 4]);
+        let stats = probe(&mut cnf, &mut Reconstruction::new(), true).unwrap();
+        assert_eq!(stats.clauses_added, 1);
+        let resolvent: HashSet<i32> = [1 << 1 | 1, 4 << 1].iter().cloned().collect();
+        assert!(cnf.clauses.values().any(|c| *c == resolvent));
+    }
+
+    #[test]
+    fn refutes_and_solves_files() {
+        let config = Config {
+            techniques: vec![Technique::Probing { hyper_binary: true }],
+            rounds: 1,
+        };
+        let cnf = parse_file("tests/cascade.cnf");
+        let result = preprocess(&cnf, &config).unwrap();
+        assert!(result.cnf.clauses.is_empty());
+        check_model(&cnf, &result);
+
+        let cnf = parse_file("tests/medium.cnf");
+        check_model(&cnf, &preprocess(&cnf, &config).unwrap());
+
+        let mut cnf = CNF::new(2, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![1, -2]);
+        cnf.add_clause(vec![-1, -2]);
+        assert!(preprocess(&cnf, &config).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+//! Subsumption and self-subsuming resolution.
+//!
+//! A clause C subsumes any clause D that contains all of its literals, and D can go. If instead C
+//! contains all of D's literals except one literal `p` of C, whose negation is in D, resolving the
+//! two on `p` gives D without `-p`, which then replaces D ("strengthening"). Both keep the formula
+//! equivalent, so nothing needs reconstructing.
+//!
+//! Candidates come from the occurrence lists of the least frequent variable of C, and a 64-bit
+//! signature of the variables of each clause rules out most of them without comparing literals.
+
+use std::collections::{HashMap, HashSet};
+
+use satyrs::cnf::CNF;
+use super::Stats;
+
+/// One bit for every variable of `clause`, modulo 64. If the signature of C has a bit the one of D
+/// doesn't, C can't subsume or strengthen D.
+pub fn signature(clause: &HashSet<i32>) -> u64 {
+    clause.iter().fold(0, |sig, lit| sig | 1 << ((lit >> 1) % 64))
+}
+
+enum Check {
+    Subsumes,
+    /// Remove this literal from D.
+    Strengthens(i32),
+    Neither,
+}
+
+fn check(c: &HashSet<i32>, d: &HashSet<i32>) -> Check {
+    let mut flipped = None;
+    for &lit in c {
+        if d.contains(&lit) {
+            continue;
+        }
+        if flipped.is_none() && d.contains(&(lit ^ 1)) {
+            flipped = Some(lit ^ 1);
+            continue;
+        }
+        return Check::Neither;
+    }
+    match flipped {
+        None => Check::Subsumes,
+        Some(lit) => Check::Strengthens(lit),
+    }
+}
+
+/// Is `clause` (of internal literals) subsumed by a clause of `cnf`? This is forward subsumption,
+/// for checking clauses before adding them.
+pub fn subsumed(cnf: &CNF, clause: &HashSet<i32>) -> bool {
+    let sig = signature(clause);
+    clause.iter().any(|lit| {
+        cnf.occurrences.get(lit).is_some_and(|occ| {
+            occ.iter().any(|id| {
+                let d = &cnf.clauses[id];
+                d.len() <= clause.len() && signature(d) & !sig == 0 && d.is_subset(clause)
+            })
+        })
+    })
+}
+
+/// Use every clause to remove or strengthen the clauses it subsumes or self-subsumes (backward
+/// subsumption), until nothing changes. Returns `None` if a clause was strengthened to nothing.
+pub fn subsume(cnf: &mut CNF) -> Option<Stats> {
+    let mut stats = Stats::default();
+    let mut signatures: HashMap<i32, u64> = cnf.clauses
+                                               .iter()
+                                               .map(|(id, c)| (*id, signature(c)))
+                                               .collect();
+    // Short clauses first, since they subsume the most
+    let mut queue: Vec<i32> = cnf.clauses.keys().cloned().collect();
+    queue.sort_by_key(|id| (std::cmp::Reverse(cnf.clauses[id].len()), std::cmp::Reverse(*id)));
+    let mut strengthened: HashSet<i32> = HashSet::new();
+
+    while let Some(id) = queue.pop() {
+        let c = match cnf.clauses.get(&id) {
+            Some(c) => c.clone(),
+            None => continue,
+        };
+        let sig = signatures[&id];
+        // Any clause C subsumes or strengthens contains the variable of each of its literals
+        let occurrences = |lit: i32| cnf.occurrences.get(&lit).map_or(0, |o| o.len());
+        let pivot = *c.iter()
+                      .min_by_key(|l| (occurrences(**l) + occurrences(**l ^ 1), **l))
+                      .unwrap();
+        let mut candidates: Vec<i32> = [pivot, pivot ^ 1].iter()
+                                                       .filter_map(|l| cnf.occurrences.get(l))
+                                                       .flat_map(|o| o.iter().cloned())
+                                                       .filter(|d| *d != id)
+                                                       .collect();
+        candidates.sort();
+        for d in candidates {
+            let clause = match cnf.clauses.get(&d) {
+                Some(clause) if clause.len() >= c.len() && sig & !signatures[&d] == 0 => clause,
+                _ => continue,
+            };
+            match check(&c, clause) {
+                Check::Subsumes => {
+                    cnf.remove_clause(d);
+                    signatures.remove(&d);
+                    stats.clauses_removed += 1;
+                }
+                Check::Strengthens(lit) => {
+                    cnf.strengthen(d, lit);
+                    if cnf.clauses[&d].is_empty() {
+                        return None;
+                    }
+                    signatures.insert(d, signature(&cnf.clauses[&d]));
+                    strengthened.insert(d);
+                    // The shorter clause may subsume others now
+                    queue.push(d);
+                }
+                Check::Neither => {}
+            }
+        }
+    }
+    // Strengthened clauses that were subsumed later count as removed only
+    stats.clauses_strengthened = strengthened.iter()
+                                             .filter(|d| cnf.clauses.contains_key(d))
+                                             .count();
+    Some(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::CNF;
+    use super::*;
+
+    fn clause(lits: &[i32]) -> HashSet<i32> {
+        lits.iter().map(|&l| if l > 0 { l << 1 } else { (-l) << 1 | 1 }).collect()
+    }
+
+    #[test]
+    fn removes_subsumed_and_duplicates() {
+        let mut cnf = CNF::new(4, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![1, 2, 3]);
+        cnf.add_clause(vec![2, 1]);
+        cnf.add_clause(vec![-1, 3, 4]);
+        let stats = subsume(&mut cnf).unwrap();
+        assert_eq!(stats.clauses_removed, 2);
+        assert_eq!(cnf.clauses.len(), 2);
+        assert!(cnf.clauses.values().any(|c| *c == clause(&[1, 2])));
+    }
+
+    #[test]
+    fn strengthens_by_self_subsumption() {
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![-1, 2, 3]);
+        cnf.add_clause(vec![-2, 3]);
+        let stats = subsume(&mut cnf).unwrap();
+        // (1 2) turns (-1 2 3) into (2 3), which (-2 3) then turns into (3), subsuming both
+        assert!(cnf.clauses.values().any(|c| *c == clause(&[3])));
+        assert_eq!(cnf.clauses.len(), 2);
+        assert!(stats.clauses_strengthened >= 1);
+        assert_eq!(stats.clauses_removed, 1);
+    }
+
+    #[test]
+    fn strengthening_to_nothing() {
+        let mut cnf = CNF::new(1, 0);
+        cnf.add_clause(vec![1]);
+        cnf.add_clause(vec![-1]);
+        assert!(subsume(&mut cnf).is_none());
+    }
+
+    #[test]
+    fn forward_subsumption() {
+        let mut cnf = CNF::new(3, 0);
+        cnf.add_clause(vec![1, -2]);
+        assert!(subsumed(&cnf, &clause(&[1, -2, 3])));
+        assert!(!subsumed(&cnf, &clause(&[1, 2, 3])));
+    }
+}
@@ -0,0 +1,273 @@
+//! Quantified Boolean formulas in prenex CNF, read from QDIMACS files and solved with QDPLL: the
+//! DPLL search of `satyrs::dpll`, with universal reduction, and branching restricted to follow
+//! the order of the quantifier prefix.
+
+use std::fs::File;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use satyrs::cnf::{self, CNF, DimacsError};
+pub use satyrs::cnf::{Prefix, Quantifier};
+
+/// A QBF: a matrix in CNF, and a prefix of quantifier blocks, outermost first. Variables that no
+/// block mentions are existentially quantified in front of everything else.
+pub struct Qbf {
+    pub cnf: CNF,
+    pub prefix: Prefix,
+}
+
+impl Qbf {
+    /// Quantifier and nesting depth of every variable, indexed by internal variable number.
+    /// Free variables live at depth 0 along with a leading existential block.
+    fn levels(&self) -> Vec<(Quantifier, usize)> {
+        let mut levels = vec![(Quantifier::Exists, 0); self.cnf.nvar as usize + 1];
+        let mut depth = 0;
+        let mut last = Quantifier::Exists;
+        for &(q, ref block) in &self.prefix {
+            // Adjacent blocks of the same quantifier are really one block
+            if q != last {
+                depth += 1;
+                last = q;
+            }
+            for v in block {
+                levels[*v as usize] = (q, depth);
+            }
+        }
+        levels
+    }
+
+    /// The variables of the outermost existential block, including free variables.
+    fn outermost_block(&self, levels: &[(Quantifier, usize)]) -> Vec<i32> {
+        (1..self.cnf.nvar + 1).filter(|v| levels[*v as usize].1 == 0).collect()
+    }
+}
+
+pub fn parse_qdimacs_file(f: File) -> Result<Qbf, DimacsError> {
+    let (cnf, prefix) = cnf::parse_qdimacs_file(f)?;
+    Ok(Qbf { cnf, prefix })
+}
+
+/// Parse QDIMACS from any buffered source, such as `io::stdin().lock()`.
+pub fn parse_qdimacs<R: BufRead>(reader: R) -> Result<Qbf, DimacsError> {
+    let (cnf, prefix) = cnf::parse_qdimacs(reader)?;
+    Ok(Qbf { cnf, prefix })
+}
+
+/// Parse a formula written out in QDIMACS, as `"p cnf 2 1\na 2 0\n1 2 0".parse::<Qbf>()`.
+impl FromStr for Qbf {
+    type Err = DimacsError;
+
+    fn from_str(s: &str) -> Result<Qbf, DimacsError> {
+        parse_qdimacs(s.as_bytes())
+    }
+}
+
+/// Decide `qbf`. If it is true, return a certificate: an assignment, as DIMACS literals, to the
+/// outermost existential block (and the free variables) under which the rest of the formula holds.
+pub fn solve(qbf: &Qbf, verbose: bool) -> Option<Vec<i32>> {
+    let levels = qbf.levels();
+    let trail = _qdpll(&qbf.cnf, &levels, Vec::new(), verbose)?;
+    let certificate = qbf.outermost_block(&levels)
+                         .into_iter()
+                         .map(|v| if trail.contains(&-v) { -v } else { v })
+                         .collect();
+    Some(certificate)
+}
+
+fn to_dimacs(lit: i32) -> i32 {
+    if lit & 1 == 0 { lit >> 1 } else { -(lit >> 1) }
+}
+
+/// Remove every universal literal that is quantified inside all the existential literals of its
+/// clause: the universal player would falsify it anyway. Returns false if a clause became empty.
+fn universal_reduction(cnf: &mut CNF, levels: &[(Quantifier, usize)]) -> bool {
+    let mut reductions = Vec::new();
+    for (id, clause) in cnf.clauses.iter() {
+        let innermost_exists = clause.iter()
+                                     .map(|l| levels[(l >> 1) as usize])
+                                     .filter(|&(q, _)| q == Quantifier::Exists)
+                                     .map(|(_, depth)| depth + 1)
+                                     .max()
+                                     .unwrap_or(0);
+        for lit in clause.iter() {
+            let (q, depth) = levels[(lit >> 1) as usize];
+            if q == Quantifier::Forall && depth + 1 > innermost_exists {
+                reductions.push((*id, *lit));
+            }
+        }
+    }
+    for (id, lit) in reductions {
+        cnf.strengthen(id, lit);
+    }
+    cnf.clauses.values().all(|c| !c.is_empty())
+}
+
+/// Returns the literals (in DIMACS form) assigned on the way to a true leaf, if the formula is
+/// true.
+fn _qdpll(cnf: &CNF,
+          levels: &[(Quantifier, usize)],
+          mut trail: Vec<i32>,
+          verbose: bool)
+          -> Option<Vec<i32>> {
+    let mut _cnf = cnf.clone();
+    loop {
+        if _cnf.clauses.is_empty() {
+            return Some(trail);
+        }
+        if !universal_reduction(&mut _cnf, levels) {
+            return None;
+        }
+
+        // After universal reduction every unit clause is existential.
+        let unit = _cnf.clauses.values().find(|c| c.len() == 1).map(|c| zeroth!(c));
+        if let Some(lit) = unit {
+            if verbose {
+                println!("Unit: {}", to_dimacs(lit));
+            }
+            trail.push(to_dimacs(lit));
+            _cnf.propagate(lit);
+            continue;
+        }
+
+        // Pure existential literals can be set true, pure universal literals false.
+        let pure = _cnf.occurrences
+                       .keys()
+                       .find(|lit| !_cnf.occurrences.contains_key(&(*lit ^ 1)))
+                       .cloned();
+        if let Some(lit) = pure {
+            let lit = match levels[(lit >> 1) as usize].0 {
+                Quantifier::Exists => lit,
+                Quantifier::Forall => lit ^ 1,
+            };
+            if verbose {
+                println!("Pure: {}", to_dimacs(lit));
+            }
+            trail.push(to_dimacs(lit));
+            _cnf.propagate(lit);
+            continue;
+        }
+        break;
+    }
+
+    // Branch on an outermost variable that still occurs, smallest first for determinism.
+    let var = _cnf.occurrences
+                  .keys()
+                  .map(|lit| lit >> 1)
+                  .min_by_key(|v| (levels[*v as usize].1, *v))
+                  .unwrap();
+    let quantifier = levels[var as usize].0;
+    if verbose {
+        println!("Splitting on {} ({:?})", var, quantifier);
+    }
+
+    let mut left = _cnf.clone();
+    left.propagate(var << 1);
+    let mut left_trail = trail.clone();
+    left_trail.push(var);
+    let left = _qdpll(&left, levels, left_trail, verbose);
+
+    match (quantifier, left) {
+        // One witness is enough for an existential
+        (Quantifier::Exists, Some(t)) => return Some(t),
+        // One counterexample is enough for a universal
+        (Quantifier::Forall, None) => return None,
+        _ => {}
+    }
+
+    let mut right = _cnf;
+    right.propagate(var << 1 | 1);
+    trail.push(-var);
+    _qdpll(&right, levels, trail, verbose)
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::DimacsErrorKind;
+    use super::*;
+
+    #[test]
+    fn parses_prefix() {
+        let qbf: Qbf = "
+            c a comment
+            p cnf 4 2
+            e 1 2 0
+            a 3 0
+            e 4 0
+            1 3 4 0
+            -2 -4 0
+        ".parse().unwrap();
+        assert_eq!(qbf.prefix,
+                   vec![(Quantifier::Exists, vec![1, 2]),
+                        (Quantifier::Forall, vec![3]),
+                        (Quantifier::Exists, vec![4])]);
+        assert_eq!(qbf.cnf.clauses.len(), 2);
+    }
+
+    #[test]
+    fn quantifier_after_clauses() {
+        let error = "
+            p cnf 2 1
+            1 2 0
+            e 1 0
+        ".parse::<Qbf>().err().unwrap();
+        assert_eq!(error.kind, DimacsErrorKind::QuantifierAfterClauses);
+    }
+
+    #[test]
+    fn forall_exists_is_true() {
+        // Whatever x is, y can be its negation
+        let qbf: Qbf = "
+            p cnf 2 2
+            a 1 0
+            e 2 0
+            1 2 0
+            -1 -2 0
+        ".parse().unwrap();
+        assert_eq!(solve(&qbf, false), Some(vec![]));
+    }
+
+    #[test]
+    fn exists_forall_is_false() {
+        // But no single y works for both values of x
+        let qbf: Qbf = "
+            p cnf 2 2
+            e 2 0
+            a 1 0
+            1 2 0
+            -1 -2 0
+        ".parse().unwrap();
+        assert_eq!(solve(&qbf, false), None);
+    }
+
+    #[test]
+    fn universal_reduction_refutes() {
+        // y is innermost in both clauses and reduces away, leaving x and -x
+        let qbf: Qbf = "
+            p cnf 2 2
+            e 1 0
+            a 2 0
+            1 2 0
+            -1 2 0
+        ".parse().unwrap();
+        assert_eq!(solve(&qbf, false), None);
+    }
+
+    #[test]
+    fn certificate_for_outermost_block() {
+        // x must be true to survive both values of y; z then follows y
+        let qbf: Qbf = "
+            p cnf 4 4
+            e 1 0
+            a 2 0
+            e 3 0
+            1 2 3 0
+            1 -2 -3 0
+            -1 -2 3 0
+            -1 2 -3 0
+        ".parse().unwrap();
+        // Variable 4 is free, so it belongs to the outermost block
+        let certificate = solve(&qbf, false).unwrap();
+        assert_eq!(certificate[0], 1);
+        assert_eq!(certificate.len(), 2);
+    }
+}
@@ -0,0 +1,320 @@
+//! Stochastic local search. Start from a random assignment and repeatedly flip a variable of some
+//! falsified clause until no clause is falsified or the flip limit runs out.
+//!
+//! Variables are picked by their break count: the number of clauses that would become false if
+//! the variable were flipped. Break counts are kept up to date incrementally on every flip, by
+//! tracking how many literals of each clause are true and, for clauses with exactly one true
+//! literal, which variable that is. Local search can never prove a formula unsatisfiable.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use satyrs::cnf::{Assignment, CNF};
+
+/// How a variable is chosen from a falsified clause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// WalkSAT/SKC: flip a variable with break count 0 if there is one; otherwise, with
+    /// probability `noise`, a random variable of the clause, and a minimal-break one otherwise.
+    WalkSat { noise: f64 },
+    /// probSAT: flip each variable with probability proportional to `(eps + break)^-cb`.
+    ProbSat { cb: f64, eps: f64 },
+}
+
+impl Strategy {
+    /// WalkSAT with the noise found to work well on random 3-SAT.
+    pub fn walksat() -> Strategy {
+        Strategy::WalkSat { noise: 0.567 }
+    }
+
+    /// probSAT with the polynomial break function tuned for random 3-SAT.
+    pub fn probsat() -> Strategy {
+        Strategy::ProbSat { cb: 2.06, eps: 0.9 }
+    }
+}
+
+/// Xorshift64* generator, so that runs are reproducible from a seed without any dependencies.
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // The all-zero state is a fixed point
+        XorShift(if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Clone)]
+struct Search {
+    /// Clauses in the internal literal representation, tautologies removed.
+    clauses: Vec<Vec<i32>>,
+    /// Clauses containing each internal literal.
+    occurrences: Vec<Vec<usize>>,
+    /// Indexed by variable; entry 0 is unused.
+    values: Vec<bool>,
+    true_count: Vec<usize>,
+    /// XOR of the variables whose literals are true in each clause. When exactly one literal is
+    /// true, this is its variable.
+    critical: Vec<usize>,
+    breaks: Vec<usize>,
+    /// Falsified clauses, and the position of each clause in that list.
+    unsat: Vec<usize>,
+    unsat_position: Vec<usize>,
+}
+
+impl Search {
+    fn new(cnf: &CNF, rng: &mut XorShift) -> Search {
+        let nvar = cnf.nvar as usize;
+        let clauses: Vec<Vec<i32>> = cnf.sorted_clauses()
+                                        .into_iter()
+                                        .filter(|c| !c.iter().any(|l| c.contains(&(l ^ 1))))
+                                        .collect();
+        let mut occurrences = vec![Vec::new(); 2 * nvar + 2];
+        for (i, clause) in clauses.iter().enumerate() {
+            for lit in clause {
+                occurrences[*lit as usize].push(i);
+            }
+        }
+        let values = (0..nvar + 1).map(|_| rng.next_u64() & 1 == 1).collect();
+        let n = clauses.len();
+        let mut search = Search {
+            clauses,
+            occurrences,
+            values,
+            true_count: Vec::new(),
+            critical: Vec::new(),
+            breaks: Vec::new(),
+            unsat: Vec::new(),
+            unsat_position: vec![0; n],
+        };
+        search.recount();
+        search
+    }
+
+    /// Compute the clause counters, break counts and falsified clauses from scratch.
+    fn recount(&mut self) {
+        let n = self.clauses.len();
+        self.true_count = vec![0; n];
+        self.critical = vec![0; n];
+        self.breaks = vec![0; self.values.len()];
+        self.unsat.clear();
+        for i in 0..n {
+            for &lit in &self.clauses[i] {
+                if self.is_true(lit) {
+                    self.true_count[i] += 1;
+                    self.critical[i] ^= (lit >> 1) as usize;
+                }
+            }
+            match self.true_count[i] {
+                0 => self.add_unsat(i),
+                1 => self.breaks[self.critical[i]] += 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn is_true(&self, lit: i32) -> bool {
+        self.values[(lit >> 1) as usize] == (lit & 1 == 0)
+    }
+
+    fn add_unsat(&mut self, clause: usize) {
+        self.unsat_position[clause] = self.unsat.len();
+        self.unsat.push(clause);
+    }
+
+    fn remove_unsat(&mut self, clause: usize) {
+        let pos = self.unsat_position[clause];
+        let last = self.unsat.pop().unwrap();
+        if last != clause {
+            self.unsat[pos] = last;
+            self.unsat_position[last] = pos;
+        }
+    }
+
+    fn flip(&mut self, var: usize) {
+        let became_true = if self.values[var] { var << 1 | 1 } else { var << 1 };
+        let became_false = became_true ^ 1;
+        self.values[var] = !self.values[var];
+
+        for k in 0..self.occurrences[became_true].len() {
+            let c = self.occurrences[became_true][k];
+            match self.true_count[c] {
+                0 => {
+                    self.remove_unsat(c);
+                    self.breaks[var] += 1;
+                }
+                1 => self.breaks[self.critical[c]] -= 1,
+                _ => {}
+            }
+            self.true_count[c] += 1;
+            self.critical[c] ^= var;
+        }
+        for k in 0..self.occurrences[became_false].len() {
+            let c = self.occurrences[became_false][k];
+            self.true_count[c] -= 1;
+            self.critical[c] ^= var;
+            match self.true_count[c] {
+                0 => {
+                    self.add_unsat(c);
+                    self.breaks[var] -= 1;
+                }
+                1 => self.breaks[self.critical[c]] += 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn pick(&self, clause: usize, strategy: Strategy, rng: &mut XorShift) -> usize {
+        let vars: Vec<usize> = self.clauses[clause].iter().map(|l| (l >> 1) as usize).collect();
+        match strategy {
+            Strategy::WalkSat { noise } => {
+                let min = vars.iter().map(|v| self.breaks[*v]).min().unwrap();
+                if min > 0 && rng.next_f64() < noise {
+                    return vars[rng.below(vars.len())];
+                }
+                let best: Vec<usize> = vars.into_iter()
+                                           .filter(|v| self.breaks[*v] == min)
+                                           .collect();
+                best[rng.below(best.len())]
+            }
+            Strategy::ProbSat { cb, eps } => {
+                let weights: Vec<f64> = vars.iter()
+                                            .map(|v| (eps + self.breaks[*v] as f64).powf(-cb))
+                                            .collect();
+                let mut r = rng.next_f64() * weights.iter().sum::<f64>();
+                for (v, w) in vars.iter().zip(weights.iter()) {
+                    if r < *w {
+                        return *v;
+                    }
+                    r -= w;
+                }
+                *vars.last().unwrap()
+            }
+        }
+    }
+}
+
+/// Search for a model of `cnf` with at most `max_flips` flips, starting from an assignment drawn
+/// from `seed`. Returns `None` if no model was found, which does not mean there is none.
+pub fn solve(cnf: &CNF, strategy: Strategy, max_flips: u64, seed: u64) -> Option<Assignment> {
+    solve_with(cnf, strategy, max_flips, seed, &AtomicBool::new(false), |_, _| {})
+}
+
+/// As `solve`, but gives up early once `stop` is set. `improved` is called with the number of
+/// falsified clauses and the assignment every time the search gets closer to a model than ever
+/// before.
+pub fn solve_with<F>(cnf: &CNF,
+                     strategy: Strategy,
+                     max_flips: u64,
+                     seed: u64,
+                     stop: &AtomicBool,
+                     mut improved: F)
+                     -> Option<Assignment>
+    where F: FnMut(usize, &Assignment)
+{
+    if cnf.clauses.values().any(|c| c.is_empty()) {
+        return None;
+    }
+    let mut rng = XorShift::new(seed);
+    let mut search = Search::new(cnf, &mut rng);
+    let mut best = usize::MAX;
+    let mut flips = 0;
+    while !search.unsat.is_empty() {
+        if search.unsat.len() < best {
+            best = search.unsat.len();
+            improved(best, &search.values[1..].to_vec());
+        }
+        // Checking every flip would be wasteful; a thousand flips take no time at all.
+        if flips == max_flips || (flips % 1024 == 0 && stop.load(Ordering::Relaxed)) {
+            return None;
+        }
+        let clause = search.unsat[rng.below(search.unsat.len())];
+        let var = search.pick(clause, strategy, &mut rng);
+        search.flip(var);
+        flips += 1;
+    }
+    Some(search.values[1..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use satyrs::cnf::test_util::{parse_file, satisfies};
+    use super::*;
+
+    #[test]
+    fn break_counts_stay_consistent() {
+        let cnf = parse_file("tests/medium.cnf");
+        let mut rng = XorShift::new(7);
+        let mut search = Search::new(&cnf, &mut rng);
+        for _ in 0..500 {
+            let var = 1 + rng.below(cnf.nvar as usize);
+            search.flip(var);
+        }
+        let mut fresh = search.clone();
+        fresh.recount();
+        assert_eq!(search.true_count, fresh.true_count);
+        assert_eq!(search.breaks, fresh.breaks);
+        let mut unsat = search.unsat.clone();
+        let mut expected = fresh.unsat.clone();
+        unsat.sort();
+        expected.sort();
+        assert_eq!(unsat, expected);
+    }
+
+    #[test]
+    fn solves_random_3sat() {
+        let cnf = parse_file("tests/uf250-01.cnf");
+        for strategy in [Strategy::walksat(), Strategy::probsat()].iter() {
+            let assn = solve(&cnf, *strategy, 1_000_000, 42).unwrap();
+            assert!(satisfies(&cnf, &assn));
+        }
+    }
+
+    #[test]
+    fn same_seed_same_model() {
+        let cnf = parse_file("tests/medium.cnf");
+        let a = solve(&cnf, Strategy::probsat(), 100_000, 3);
+        let b = solve(&cnf, Strategy::probsat(), 100_000, 3);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn gives_up_on_unsatisfiable() {
+        let mut cnf = CNF::new(2, 0);
+        cnf.add_clause(vec![1, 2]);
+        cnf.add_clause(vec![-1, 2]);
+        cnf.add_clause(vec![1, -2]);
+        cnf.add_clause(vec![-1, -2]);
+        assert_eq!(solve(&cnf, Strategy::walksat(), 1000, 1), None);
+    }
+
+    #[test]
+    fn reports_improvements_and_stops() {
+        let cnf = parse_file("tests/uf250-01.cnf");
+        let mut progress = Vec::new();
+        let stop = AtomicBool::new(true);
+        let result = solve_with(&cnf, Strategy::walksat(), u64::MAX, 5, &stop, |n, assn| {
+            assert_eq!(assn.len(), cnf.nvar as usize);
+            progress.push(n)
+        });
+        // Stopped before the first flip, having reported only the starting point
+        assert_eq!(result, None);
+        assert_eq!(progress.len(), 1);
+        assert!(progress[0] > 0);
+    }
+}